@@ -0,0 +1,122 @@
+use crate::slurm::{JobState, JobUsage};
+use crate::submitit::SbatchRequest;
+
+// How much of a requested resource was used, e.g. 600 of 3600 seconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Use {
+    pub used: u64,
+    pub requested: u64,
+}
+
+impl Use {
+    fn new(used: Option<u64>, requested: Option<u64>) -> Option<Self> {
+        Some(Use {
+            used: used?,
+            requested: requested?,
+        })
+    }
+
+    pub fn ratio(self) -> Option<f64> {
+        (self.requested > 0).then(|| self.used as f64 / self.requested as f64)
+    }
+
+    // How many times more was requested than used, None if nothing was used.
+    fn overrequest(self) -> Option<f64> {
+        (self.used > 0).then(|| self.requested as f64 / self.used as f64)
+    }
+}
+
+fn add(a: Option<Use>, b: Option<Use>) -> Option<Use> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(Use {
+            used: a.used + b.used,
+            requested: a.requested + b.requested,
+        }),
+        (a, b) => a.or(b),
+    }
+}
+
+fn add_count(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a + b),
+        (a, b) => a.or(b),
+    }
+}
+
+// Requested against used resources of a job, like seff reports them. The
+// cpu time is compared against what the allocated cpus could have done in
+// the elapsed time.
+#[derive(Debug, Clone, Default)]
+pub struct Efficiency {
+    pub time: Option<Use>,
+    pub mem: Option<Use>,
+    pub cpu: Option<Use>,
+    pub requested_gpus: Option<u64>,
+    pub allocated_gpus: Option<u64>,
+    // Elapsed time times allocated gpus.
+    pub gpu_seconds: Option<u64>,
+    // Jobs that failed or are still running did not use what they would
+    // have needed, so only completed jobs are flagged and summed up.
+    pub completed: bool,
+}
+
+impl Efficiency {
+    pub fn new(request: Option<&SbatchRequest>, usage: Option<&JobUsage>) -> Self {
+        let Some(usage) = usage else {
+            return Efficiency {
+                requested_gpus: request.and_then(SbatchRequest::gpu_count),
+                ..Efficiency::default()
+            };
+        };
+        let requested_mem = request.and_then(|request| request.mem_bytes(usage.cpus, usage.gpus));
+        Efficiency {
+            time: Use::new(
+                usage.elapsed,
+                request.and_then(|request| request.time_limit),
+            ),
+            mem: Use::new(usage.max_rss, requested_mem),
+            cpu: Use::new(
+                usage.cpu_time,
+                usage
+                    .elapsed
+                    .zip(usage.cpus)
+                    .map(|(elapsed, cpus)| elapsed * cpus),
+            ),
+            requested_gpus: request.and_then(SbatchRequest::gpu_count),
+            allocated_gpus: usage.gpus,
+            gpu_seconds: usage
+                .elapsed
+                .zip(usage.gpus)
+                .map(|(elapsed, gpus)| elapsed * gpus),
+            completed: usage.state == JobState::Completed,
+        }
+    }
+
+    // Sums up the completed jobs of a sweep, so the ratios are weighted by
+    // how much each job requested.
+    pub fn total<'a>(jobs: impl IntoIterator<Item = &'a Efficiency>) -> Self {
+        jobs.into_iter()
+            .filter(|job| job.completed)
+            .fold(Efficiency::default(), |total, job| Efficiency {
+                time: add(total.time, job.time),
+                mem: add(total.mem, job.mem),
+                cpu: add(total.cpu, job.cpu),
+                gpu_seconds: add_count(total.gpu_seconds, job.gpu_seconds),
+                completed: true,
+                ..Efficiency::default()
+            })
+    }
+
+    // Memory and time that were requested at least factor times more than
+    // they were used, as e.g. [("mem", 21.3)].
+    pub fn overrequested(&self, factor: f64) -> Vec<(&'static str, f64)> {
+        if !self.completed {
+            return Vec::new();
+        }
+        [("mem", self.mem), ("time", self.time)]
+            .into_iter()
+            .filter_map(|(name, resource)| Some((name, resource?.overrequest()?)))
+            .filter(|(_, overrequest)| *overrequest >= factor)
+            .collect()
+    }
+}
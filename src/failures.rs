@@ -0,0 +1,194 @@
+use regex::Regex;
+use std::fmt;
+use std::sync::LazyLock;
+
+const TRACEBACK_START: &str = "Traceback (most recent call last):";
+
+// Checked in order, the first class with a matching line wins. A CUDA OOM for
+// example also shows up as a Python exception, but is the more useful answer.
+static PATTERNS: LazyLock<Vec<(FailureClass, Regex)>> = LazyLock::new(|| {
+    [
+        (
+            FailureClass::CudaOutOfMemory,
+            r"CUDA out of memory|CUDA error: out of memory|OutOfMemoryError",
+        ),
+        (
+            FailureClass::Nccl,
+            r"NCCL (error|Error)|nccl(System|Internal|Unhandled\w*)Error|ProcessGroupNCCL.*(timeout|Timeout|error)",
+        ),
+        (FailureClass::TimeLimit, r"DUE TO TIME LIMIT"),
+        (FailureClass::Cancelled, r"\*\*\* .*CANCELLED AT"),
+        (
+            FailureClass::Segfault,
+            r"Segmentation fault|SIGSEGV|signal 11\b",
+        ),
+    ]
+    .into_iter()
+    .map(|(class, pattern)| (class, Regex::new(pattern).unwrap()))
+    .collect()
+});
+
+static SUBMITIT_FAILURE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"has failed").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FailureClass {
+    CudaOutOfMemory,
+    Nccl,
+    TimeLimit,
+    Cancelled,
+    Segfault,
+    PythonException,
+    SubmititFailure,
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FailureClass::CudaOutOfMemory => "CUDA out of memory",
+            FailureClass::Nccl => "NCCL error",
+            FailureClass::TimeLimit => "time limit",
+            FailureClass::Cancelled => "cancelled",
+            FailureClass::Segfault => "segfault",
+            FailureClass::PythonException => "Python exception",
+            FailureClass::SubmititFailure => "submitit failure",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub class: FailureClass,
+    // The line that gave the failure away, e.g. the final exception line.
+    pub key_line: String,
+}
+
+// The line stating the exception at the end of the last traceback, e.g.
+// "ValueError: invalid literal for int()", without a launcher prefix. None if
+// the log ends within the traceback.
+pub fn final_exception_line(log: &str) -> Option<String> {
+    let line = extract_tracebacks(log).pop()?.pop()?;
+    (!line.is_empty() && !line.starts_with(char::is_whitespace)).then_some(line)
+}
+
+// Figures out why a job died from (the end of) its stderr log.
+pub fn classify(err_log: &str) -> Option<Failure> {
+    let lines: Vec<&str> = err_log.lines().collect();
+    for (class, pattern) in PATTERNS.iter() {
+        // The last occurrence is closest to the actual cause of death.
+        if let Some(line) = lines.iter().rev().find(|line| pattern.is_match(line)) {
+            return Some(Failure {
+                class: *class,
+                key_line: line.trim().to_string(),
+            });
+        }
+    }
+    if let Some(line) = final_exception_line(err_log) {
+        return Some(Failure {
+            class: FailureClass::PythonException,
+            key_line: line.trim().to_string(),
+        });
+    }
+    lines
+        .iter()
+        .rev()
+        .find(|line| SUBMITIT_FAILURE.is_match(line))
+        .map(|line| Failure {
+            class: FailureClass::SubmititFailure,
+            key_line: line.trim().to_string(),
+        })
+}
+
+// Printed between the tracebacks of chained exceptions.
+const CHAIN_MARKERS: [&str; 2] = [
+    "During handling of the above exception, another exception occurred:",
+    "The above exception was the direct cause of the following exception:",
+];
+
+// Extracts every traceback in the log. Chained exceptions are kept together
+// in a single block. Launchers like torchrun prefix every line (e.g. with
+// "[rank0]: "), such a prefix is taken from the first line and stripped.
+pub fn extract_tracebacks(log: &str) -> Vec<Vec<String>> {
+    let lines: Vec<&str> = log.lines().collect();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(start) = lines[i].find(TRACEBACK_START) else {
+            i += 1;
+            continue;
+        };
+        let prefix = &lines[i][..start];
+        let unprefixed = |line: &str| line.strip_prefix(prefix).unwrap_or(line).to_string();
+
+        let mut block = Vec::new();
+        loop {
+            // The header, the indented frames and the exception line.
+            block.push(unprefixed(lines[i]));
+            i += 1;
+            while i < lines.len() {
+                let line = unprefixed(lines[i]);
+                i += 1;
+                let is_exception_line = !line.is_empty() && !line.starts_with(char::is_whitespace);
+                block.push(line);
+                if is_exception_line {
+                    break;
+                }
+            }
+
+            // Continue with the next traceback if it is chained to this one.
+            let mut next = i;
+            while next < lines.len() && unprefixed(lines[next]).trim().is_empty() {
+                next += 1;
+            }
+            let chained = next + 1 < lines.len()
+                && CHAIN_MARKERS.contains(&unprefixed(lines[next]).trim())
+                && lines[next + 1..]
+                    .iter()
+                    .find(|line| !unprefixed(line).trim().is_empty())
+                    .is_some_and(|line| line.contains(TRACEBACK_START));
+            if !chained {
+                break;
+            }
+            block.push(String::new());
+            block.push(unprefixed(lines[next]));
+            block.push(String::new());
+            i = next + 1;
+            while unprefixed(lines[i]).trim().is_empty() {
+                i += 1;
+            }
+        }
+        blocks.push(block);
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exception_line_without_launcher_prefix() {
+        let log = "\
+[rank0]: Traceback (most recent call last):
+[rank0]:   File \"train.py\", line 3, in <module>
+[rank0]:     main()
+[rank0]: ValueError: bad learning rate
+";
+        let failure = classify(log).unwrap();
+        assert_eq!(failure.class, FailureClass::PythonException);
+        assert_eq!(failure.key_line, "ValueError: bad learning rate");
+    }
+
+    #[test]
+    fn nccl_warnings_do_not_outrank_the_exception() {
+        let log = "\
+NCCL WARN Call to connect returned Connection refused, retrying
+Traceback (most recent call last):
+  File \"train.py\", line 3, in <module>
+KeyError: 'lr'
+";
+        let failure = classify(log).unwrap();
+        assert_eq!(failure.class, FailureClass::PythonException);
+        assert_eq!(failure.key_line, "KeyError: 'lr'");
+    }
+}
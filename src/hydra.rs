@@ -0,0 +1,195 @@
+use crate::jobid::JobId;
+use crate::{
+    get_subdirectories, FileNotFoundSnafu, HydraDirNotFoundSnafu, InvalidFilterSnafu, PResult,
+    ProgramError,
+};
+use regex::Regex;
+use snafu::ResultExt;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Each job of a multirun gets its own Hydra output directory next to the
+// submitit logs, named after the job number:
+//   multirun/YYYY-MM-DD/hh-mm-ss/<job_num>/.hydra/{config,overrides,hydra}.yaml
+pub fn hydra_dir(job_dir: &Path) -> PathBuf {
+    job_dir.join(".hydra")
+}
+
+// hydra.job.id in .hydra/hydra.yaml. The submitit launcher sets it to the
+// Slurm id of the task, e.g. 12345_3.
+fn hydra_job_id(job_dir: &Path) -> Option<String> {
+    let hydra_yaml = fs::read_to_string(hydra_dir(job_dir).join("hydra.yaml")).ok()?;
+    hydra_field(&hydra_yaml, "job", "id")
+}
+
+// The Hydra job directories of a sweep by the job id they belong to.
+fn index_job_dirs(sweep_dir: &Path) -> HashMap<String, PathBuf> {
+    get_subdirectories(sweep_dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|dir| {
+            dir.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.parse::<u32>().is_ok())
+        })
+        .filter_map(|dir| Some((hydra_job_id(&dir)?, dir)))
+        .collect()
+}
+
+// Indexed by the first lookup that misses the directory of the array index.
+type SweepJobDirs = Arc<OnceLock<HashMap<String, PathBuf>>>;
+
+// Finds the Hydra job directories of submitit jobs. The job number usually
+// equals the array index, so that directory is checked first. Otherwise the
+// job ids of all job directories of the sweep are read, once per sweep.
+#[derive(Debug, Default)]
+pub struct JobDirs {
+    sweeps: Mutex<HashMap<PathBuf, SweepJobDirs>>,
+}
+
+impl JobDirs {
+    pub fn find(&self, sweep_dir: &Path, id: &JobId) -> PResult<PathBuf> {
+        let wanted = id.to_string();
+        if let Some(index) = id.array_index {
+            let candidate = sweep_dir.join(index.to_string());
+            if hydra_job_id(&candidate).as_deref() == Some(wanted.as_str()) {
+                return Ok(candidate);
+            }
+        }
+        // Jobs of the same sweep are often looked up in parallel, they wait
+        // for a single thread to index it.
+        let sweep = Arc::clone(
+            self.sweeps
+                .lock()
+                .unwrap()
+                .entry(sweep_dir.to_path_buf())
+                .or_default(),
+        );
+        let dirs = sweep.get_or_init(|| index_job_dirs(sweep_dir));
+        dirs.get(&wanted).cloned().ok_or_else(|| {
+            HydraDirNotFoundSnafu {
+                id: wanted,
+                sweep_dir: sweep_dir.to_path_buf(),
+            }
+            .build()
+        })
+    }
+}
+
+fn unquote(s: &str) -> &str {
+    let s = s.trim();
+    for quote in ['\'', '"'] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    s
+}
+
+// Hydra writes overrides.yaml as a plain list of strings, e.g.
+//   - lr=0.001
+//   - model.name=resnet18
+pub fn read_overrides(job_dir: &Path) -> PResult<Vec<String>> {
+    let path = hydra_dir(job_dir).join("overrides.yaml");
+    let content = fs::read_to_string(&path).context(FileNotFoundSnafu { path })?;
+    Ok(content
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("- "))
+        .map(|item| unquote(item).to_string())
+        .collect())
+}
+
+// Lines of a section of the hydra: config, like hydra.job. Hydra dumps the
+// config with two spaces of indentation per level, so they are the lines
+// indented by four spaces after "  <section>:".
+fn section_lines<'a>(yaml: &'a str, section: &str) -> impl Iterator<Item = &'a str> {
+    let header = format!("  {section}:");
+    let mut lines = yaml.lines();
+    let found = lines.by_ref().find(|line| *line == header).is_some();
+    lines.take_while(move |line| found && line.starts_with("    "))
+}
+
+// Reads a list of hydra.overrides from multirun.yaml or a job's hydra.yaml,
+// "task" for the ones given on the command line or "hydra" for the ones
+// configuring Hydra itself, e.g.
+//   hydra:
+//     overrides:
+//       task:
+//       - lr=0.1,0.01
+//       - model=resnet18
+pub fn overrides_list(yaml: &str, list: &str) -> Vec<String> {
+    let header = format!("    {list}:");
+    let mut lines = section_lines(yaml, "overrides");
+    if !lines.by_ref().any(|line| line == header) {
+        return Vec::new();
+    }
+    lines
+        .map_while(|line| line.strip_prefix("    - "))
+        .map(|item| item.trim().to_string())
+        .collect()
+}
+
+// Reads a scalar like hydra.job.name or hydra.runtime.cwd.
+pub fn hydra_field(yaml: &str, section: &str, key: &str) -> Option<String> {
+    let prefix = format!("    {key}: ");
+    section_lines(yaml, section)
+        .find_map(|line| line.strip_prefix(prefix.as_str()))
+        .map(|value| unquote(value).to_string())
+}
+
+pub fn read_hydra_file(job_dir: &Path, name: &str) -> PResult<String> {
+    let path = hydra_dir(job_dir).join(name);
+    fs::read_to_string(&path).context(FileNotFoundSnafu { path })
+}
+
+// Splits an override like lr=0.001, +trainer.gpus=2 or ++seed=1 into its key
+// and value. Deletions like ~key have no value and yield None.
+pub fn split_override(o: &str) -> Option<(&str, &str)> {
+    let (key, value) = o.split_once('=')?;
+    Some((key.trim_start_matches('+'), unquote(value)))
+}
+
+// A --where KEY=VALUE filter on the overrides of a job. The value may contain
+// the wildcards * and ?.
+#[derive(Debug, Clone)]
+pub struct OverrideFilter {
+    key: String,
+    value: Regex,
+}
+
+impl FromStr for OverrideFilter {
+    type Err = ProgramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((key, value)) = split_override(s).filter(|(key, _)| !key.is_empty()) else {
+            return InvalidFilterSnafu {
+                filter: s.to_string(),
+            }
+            .fail();
+        };
+        let pattern: String = value
+            .chars()
+            .map(|c| match c {
+                '*' => ".*".to_string(),
+                '?' => ".".to_string(),
+                c => regex::escape(&c.to_string()),
+            })
+            .collect();
+        Ok(OverrideFilter {
+            key: key.to_string(),
+            value: Regex::new(&format!("^{pattern}$")).unwrap(),
+        })
+    }
+}
+
+impl OverrideFilter {
+    pub fn matches(&self, overrides: &[String]) -> bool {
+        overrides
+            .iter()
+            .filter_map(|o| split_override(o))
+            .any(|(key, value)| key == self.key && self.value.is_match(value))
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{get_subdirectories, IndexWriteSnafu, PResult};
+use snafu::ResultExt;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::UNIX_EPOCH;
+
+const INDEX_FILE_NAME: &str = ".viewlogs-index";
+const INDEX_HEADER: &str = "viewlogs-index 1";
+
+// The index stores, per sweep directory (YYYY-MM-DD/hh-mm-ss relative to the
+// root), the mtime of its .submitit directory and the job directories found
+// in it. A .submitit directory only has to be listed again if its mtime
+// changed, i.e. if jobs were added or removed.
+struct SweepEntry {
+    mtime: u128,
+    jobs: Vec<String>,
+}
+
+type Index = BTreeMap<PathBuf, SweepEntry>;
+
+fn index_path(root: &Path) -> PathBuf {
+    root.join(INDEX_FILE_NAME)
+}
+
+fn mtime_nanos(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+// A missing, unreadable or outdated index is treated as empty, it will
+// simply be rebuilt.
+fn read_index(root: &Path) -> Index {
+    let mut index = Index::new();
+    let Ok(content) = fs::read_to_string(index_path(root)) else {
+        return index;
+    };
+    let mut lines = content.lines();
+    if lines.next() != Some(INDEX_HEADER) {
+        return index;
+    }
+    for line in lines {
+        let mut fields = line.split('\t');
+        let (Some(sweep), Some(mtime), Some(jobs)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Index::new();
+        };
+        let Ok(mtime) = mtime.parse() else {
+            return Index::new();
+        };
+        let jobs = jobs
+            .split(',')
+            .filter(|job| !job.is_empty())
+            .map(|job| job.to_string())
+            .collect();
+        index.insert(PathBuf::from(sweep), SweepEntry { mtime, jobs });
+    }
+    index
+}
+
+fn write_index(root: &Path, index: &Index) -> io::Result<()> {
+    // Write to a temporary file first, so concurrent readers never see a
+    // partially written index. Each process writes its own, as several runs
+    // may update the index of a shared root at once.
+    let tmp_path = root.join(format!("{INDEX_FILE_NAME}.{}.tmp", process::id()));
+    let mut file = fs::File::create(&tmp_path)?;
+    let result = write_entries(&mut file, index).and_then(|()| file.sync_all());
+    match result {
+        Ok(()) => fs::rename(tmp_path, index_path(root)),
+        Err(e) => {
+            let _ = fs::remove_file(tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_entries(file: &mut fs::File, index: &Index) -> io::Result<()> {
+    writeln!(file, "{INDEX_HEADER}")?;
+    for (sweep, entry) in index {
+        writeln!(
+            file,
+            "{}\t{}\t{}",
+            sweep.display(),
+            entry.mtime,
+            entry.jobs.join(",")
+        )?;
+    }
+    Ok(())
+}
+
+// Returns the refreshed index and whether it differs from the previous one.
+fn scan_root(root: &Path, previous: &Index) -> PResult<(Index, bool)> {
+    let mut index = Index::new();
+    let mut changed = false;
+    for ymd in get_subdirectories(root)? {
+        for hms in get_subdirectories(ymd)? {
+            let submitit_dir = hms.join(".submitit");
+            let Some(mtime) = mtime_nanos(&submitit_dir) else {
+                continue;
+            };
+            let sweep = hms.strip_prefix(root).unwrap_or(&hms).to_path_buf();
+            if let Some(entry) = previous.get(&sweep).filter(|entry| entry.mtime == mtime) {
+                index.insert(
+                    sweep,
+                    SweepEntry {
+                        mtime,
+                        jobs: entry.jobs.clone(),
+                    },
+                );
+                continue;
+            }
+            let mut jobs: Vec<String> = get_subdirectories(&submitit_dir)?
+                .iter()
+                .filter_map(|job| Some(job.file_name()?.to_str()?.to_string()))
+                .collect();
+            jobs.sort();
+            index.insert(sweep, SweepEntry { mtime, jobs });
+            changed = true;
+        }
+    }
+    // Sweeps that were deleted also count as a change.
+    changed |= index.len() != previous.len();
+    Ok((index, changed))
+}
+
+fn job_dirs(root: &Path, index: &Index) -> Vec<PathBuf> {
+    index
+        .iter()
+        .flat_map(|(sweep, entry)| {
+            let submitit_dir = root.join(sweep).join(".submitit");
+            entry.jobs.iter().map(move |job| submitit_dir.join(job))
+        })
+        .collect()
+}
+
+// Returns the directories of all jobs below the root. With the cache enabled,
+// the on-disk index is refreshed incrementally and written back; failing to
+// write it (e.g. on a read-only root) is not an error.
+pub fn root_job_dirs(root: &Path, use_cache: bool) -> PResult<Vec<PathBuf>> {
+    if !use_cache {
+        let (index, _) = scan_root(root, &Index::new())?;
+        return Ok(job_dirs(root, &index));
+    }
+    let (index, changed) = scan_root(root, &read_index(root))?;
+    if changed {
+        let _ = write_index(root, &index);
+    }
+    Ok(job_dirs(root, &index))
+}
+
+// Rebuilds the index of the root from scratch and returns the number of jobs
+// in it.
+pub fn reindex(root: &Path) -> PResult<usize> {
+    let (index, _) = scan_root(root, &Index::new())?;
+    write_index(root, &index).context(IndexWriteSnafu {
+        path: index_path(root),
+    })?;
+    Ok(index.values().map(|entry| entry.jobs.len()).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::{Duration, SystemTime};
+
+    // A root below the temp directory, removed when dropped.
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new(name: &str) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("viewlogs-index-{name}-{}", process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            TempRoot(root)
+        }
+
+        fn add_job(&self, sweep: &str, job: &str) -> PathBuf {
+            let submitit_dir = self.0.join(sweep).join(".submitit");
+            fs::create_dir_all(submitit_dir.join(job)).unwrap();
+            submitit_dir
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn set_mtime(dir: &Path, seconds: u64) {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+        File::open(dir).unwrap().set_modified(time).unwrap();
+    }
+
+    fn job_names(dirs: Vec<PathBuf>) -> Vec<String> {
+        dirs.iter()
+            .map(|dir| dir.file_name().unwrap().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn written_index_reads_back_unchanged() {
+        let root = TempRoot::new("round-trip");
+        root.add_job("2024-01-01/10-00-00", "1_1");
+        root.add_job("2024-01-01/10-00-00", "1_0");
+        root.add_job("2024-01-02/09-00-00", "2");
+
+        let dirs = root_job_dirs(&root.0, true).unwrap();
+        assert_eq!(job_names(dirs), ["1_0", "1_1", "2"]);
+        let index = read_index(&root.0);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index[Path::new("2024-01-01/10-00-00")].jobs, ["1_0", "1_1"]);
+        let (_, changed) = scan_root(&root.0, &index).unwrap();
+        assert!(!changed);
+        // Only the index itself is left, no temporary files.
+        let files: Vec<_> = fs::read_dir(&root.0)
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.starts_with(INDEX_FILE_NAME))
+            .collect();
+        assert_eq!(files, [INDEX_FILE_NAME]);
+    }
+
+    #[test]
+    fn sweeps_are_only_listed_again_when_their_mtime_changes() {
+        let root = TempRoot::new("mtime");
+        let submitit_dir = root.add_job("2024-01-01/10-00-00", "1_0");
+        set_mtime(&submitit_dir, 1000);
+        assert_eq!(job_names(root_job_dirs(&root.0, true).unwrap()), ["1_0"]);
+
+        // The index is trusted as long as the mtime is the same.
+        root.add_job("2024-01-01/10-00-00", "1_1");
+        set_mtime(&submitit_dir, 1000);
+        assert_eq!(job_names(root_job_dirs(&root.0, true).unwrap()), ["1_0"]);
+        assert_eq!(
+            job_names(root_job_dirs(&root.0, false).unwrap()),
+            ["1_0", "1_1"]
+        );
+
+        set_mtime(&submitit_dir, 2000);
+        assert_eq!(
+            job_names(root_job_dirs(&root.0, true).unwrap()),
+            ["1_0", "1_1"]
+        );
+        assert_eq!(
+            read_index(&root.0).values().next().unwrap().mtime,
+            2000 * 1_000_000_000
+        );
+    }
+
+    #[test]
+    fn deleted_sweeps_count_as_a_change() {
+        let root = TempRoot::new("deleted");
+        root.add_job("2024-01-01/10-00-00", "1_0");
+        root.add_job("2024-01-02/10-00-00", "2_0");
+        root_job_dirs(&root.0, true).unwrap();
+        fs::remove_dir_all(root.0.join("2024-01-02")).unwrap();
+        let (index, changed) = scan_root(&root.0, &read_index(&root.0)).unwrap();
+        assert!(changed);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn unknown_or_broken_indexes_are_empty() {
+        let root = TempRoot::new("broken");
+        fs::write(index_path(&root.0), "viewlogs-index 0\nsweep\t1\ta\n").unwrap();
+        assert!(read_index(&root.0).is_empty());
+        fs::write(
+            index_path(&root.0),
+            format!("{INDEX_HEADER}\nsweep\tsoon\ta\n"),
+        )
+        .unwrap();
+        assert!(read_index(&root.0).is_empty());
+    }
+}
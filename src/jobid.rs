@@ -0,0 +1,266 @@
+use crate::{InvalidJobIdSnafu, ProgramError};
+use std::fmt;
+use std::str::FromStr;
+
+// Slurm id of a submitit job. Tasks of a job array are named
+// <job_id>_<array_index>, plain jobs just <job_id>. Ordering is numeric by
+// job id first and array index second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId {
+    pub job: u64,
+    pub array_index: Option<u32>,
+}
+
+impl FromStr for JobId {
+    type Err = ProgramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidJobIdSnafu { id: s.to_string() }.build();
+        let (job, array_index) = match s.split_once('_') {
+            Some((job, index)) => (job, Some(index.parse().map_err(|_| invalid())?)),
+            None => (s, None),
+        };
+        Ok(JobId {
+            job: job.parse().map_err(|_| invalid())?,
+            array_index,
+        })
+    }
+}
+
+impl fmt::Display for JobId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.array_index {
+            Some(index) => write!(f, "{}_{index}", self.job),
+            None => write!(f, "{}", self.job),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Tasks {
+    All,
+    // Inclusive ranges of array indices.
+    Ranges(Vec<(u32, u32)>),
+}
+
+// Jobs picked on the command line, one of
+//   12345          the job and all of its array tasks
+//   12345_3        a single array task
+//   12345_[0-7]    array tasks in Slurm's range syntax, e.g. [0-3,5,8-9]
+#[derive(Debug, Clone)]
+pub struct JobSelector {
+    pub job: u64,
+    tasks: Tasks,
+}
+
+impl JobSelector {
+    pub fn matches(&self, id: &JobId) -> bool {
+        if id.job != self.job {
+            return false;
+        }
+        match (&self.tasks, id.array_index) {
+            (Tasks::All, _) => true,
+            (Tasks::Ranges(ranges), Some(index)) => ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&index)),
+            (Tasks::Ranges(_), None) => false,
+        }
+    }
+}
+
+fn parse_ranges(s: &str) -> Option<Vec<(u32, u32)>> {
+    s.split(',')
+        .map(|range| match range.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+                (start <= end).then_some((start, end))
+            }
+            None => {
+                let index = range.parse().ok()?;
+                Some((index, index))
+            }
+        })
+        .collect()
+}
+
+impl FromStr for JobSelector {
+    type Err = ProgramError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidJobIdSnafu { id: s.to_string() }.build();
+        let Some((job, tasks)) = s.split_once('_') else {
+            return Ok(JobSelector {
+                job: s.parse().map_err(|_| invalid())?,
+                tasks: Tasks::All,
+            });
+        };
+        let ranges = match tasks.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            Some(ranges) => parse_ranges(ranges),
+            None => parse_ranges(tasks).filter(|ranges| ranges.len() == 1),
+        };
+        Ok(JobSelector {
+            job: job.parse().map_err(|_| invalid())?,
+            tasks: Tasks::Ranges(ranges.ok_or_else(invalid)?),
+        })
+    }
+}
+
+impl fmt::Display for JobSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.tasks {
+            Tasks::All => write!(f, "{}", self.job),
+            Tasks::Ranges(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => {
+                write!(f, "{}_{}", self.job, ranges[0].0)
+            }
+            Tasks::Ranges(ranges) => {
+                let ranges: Vec<String> = ranges
+                    .iter()
+                    .map(|(start, end)| match start == end {
+                        true => start.to_string(),
+                        false => format!("{start}-{end}"),
+                    })
+                    .collect();
+                write!(f, "{}_[{}]", self.job, ranges.join(","))
+            }
+        }
+    }
+}
+
+const MAX_SUGGESTIONS: usize = 3;
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl JobSelector {
+    // Known ids close to the selector, for "did you mean" hints. If the job
+    // exists but not the requested tasks, its tasks are suggested. Otherwise
+    // we look for job ids that are a typo away, closest numbers first.
+    pub fn suggestions<'a>(&self, known: impl Iterator<Item = &'a JobId> + Clone) -> Vec<String> {
+        let tasks: Vec<String> = known
+            .clone()
+            .filter(|id| id.job == self.job)
+            .take(MAX_SUGGESTIONS)
+            .map(|id| id.to_string())
+            .collect();
+        if !tasks.is_empty() {
+            return tasks;
+        }
+
+        let target = self.job.to_string();
+        let mut jobs: Vec<(usize, u64, u64)> = known
+            .map(|id| id.job)
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .map(|job| {
+                let distance = edit_distance(&target, &job.to_string());
+                (distance, job.abs_diff(self.job), job)
+            })
+            .filter(|(distance, _, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+            .collect();
+        jobs.sort();
+        jobs.into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, _, job)| job.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(s: &str) -> JobId {
+        s.parse().unwrap()
+    }
+
+    fn selector(s: &str) -> JobSelector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn a_job_selects_all_its_tasks() {
+        let all = selector("12345");
+        assert!(all.matches(&id("12345")));
+        assert!(all.matches(&id("12345_3")));
+        assert!(!all.matches(&id("12346_3")));
+    }
+
+    #[test]
+    fn ranges_select_array_tasks() {
+        let ranges = selector("12345_[0-3,5]");
+        for task in ["12345_0", "12345_3", "12345_5"] {
+            assert!(ranges.matches(&id(task)), "{task}");
+        }
+        for task in ["12345_4", "12345_6", "12345", "12346_0"] {
+            assert!(!ranges.matches(&id(task)), "{task}");
+        }
+        // A single range does not need brackets.
+        let bare = selector("12345_0-3");
+        assert!(bare.matches(&id("12345_2")));
+        assert!(!bare.matches(&id("12345_4")));
+    }
+
+    #[test]
+    fn rejects_malformed_selectors() {
+        for s in [
+            "",
+            "abc",
+            "12345_",
+            "12345_[]",
+            "12345_[3-1]",
+            "12345_1_2",
+            "12345_1,2",
+            "12345_[0-",
+        ] {
+            assert!(s.parse::<JobSelector>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn displays_as_parsed() {
+        for s in ["12345", "12345_3", "12345_[0-3,5]", "12345_[0-3]"] {
+            assert_eq!(selector(s).to_string(), s);
+        }
+        assert_eq!(selector("12345_0-3").to_string(), "12345_[0-3]");
+        assert_eq!(selector("12345_[3]").to_string(), "12345_3");
+        assert_eq!(id("12345_3").to_string(), "12345_3");
+    }
+
+    #[test]
+    fn suggests_tasks_of_the_same_job() {
+        let known = [id("12345_0"), id("12345_1"), id("12346_7")];
+        assert_eq!(
+            selector("12345_7").suggestions(known.iter()),
+            ["12345_0", "12345_1"]
+        );
+    }
+
+    #[test]
+    fn ranks_suggestions_by_edit_then_numeric_distance() {
+        let known = [
+            id("21345"),
+            id("12350"),
+            id("12335_0"),
+            id("12346"),
+            id("99999"),
+        ];
+        // 12350 is numerically closer than 12335, but two edits away.
+        assert_eq!(
+            selector("12345").suggestions(known.iter()),
+            ["12346", "12335", "12350"]
+        );
+        assert!(selector("55555").suggestions(known.iter()).is_empty());
+    }
+}
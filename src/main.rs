@@ -1,64 +1,368 @@
-use clap::{Parser, Subcommand};
+mod efficiency;
+mod failures;
+mod hydra;
+mod index;
+mod jobid;
+mod metrics;
+mod output;
+mod pickle;
+mod plot;
+mod resubmit;
+mod slurm;
+mod submitit;
+mod sweep;
+mod table;
+mod tui;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use hydra::OverrideFilter;
+use jobid::{JobId, JobSelector};
+use metrics::Stat;
+use output::{OutputFormat, RecordFormat, RecordWriter};
+use rayon::prelude::*;
 use regex::Regex;
+use serde_json::Value;
+use slurm::{JobInfo, JobState};
 use snafu::{ResultExt, Snafu};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::fs::{self, File};
-use std::io::{self, Read};
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::mem;
 use std::path::{Path, PathBuf};
-use std::process::Command as ProcCommand;
+use std::process::{Child, Command as ProcCommand, ExitCode, ExitStatus, Stdio};
+use std::sync::{mpsc, LazyLock, Mutex};
+use std::thread;
+use std::time::Duration;
+use sweep::{Outcome, SweepSelector};
+
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Hydra output directories we look for when no root is given explicitly.
+const ROOT_DIR_NAMES: [&str; 2] = ["multirun", "outputs"];
 
 #[derive(Debug, Snafu)]
-enum ProgramError {
+#[snafu(visibility(pub(crate)))]
+pub enum ProgramError {
     #[snafu(display("Could not find file {}.", path.display()))]
     FileNotFound { source: io::Error, path: PathBuf },
     #[snafu(display("Could not find log in {} with ending {}.", dir.display(), ending))]
     LogNotFound { dir: PathBuf, ending: String },
+    #[snafu(display("Could not find the Hydra output directory of job {id} in {}.", sweep_dir.display()))]
+    HydraDirNotFound { id: String, sweep_dir: PathBuf },
+    #[snafu(display(
+        "Invalid filter {filter}, expected KEY=VALUE, e.g. lr=0.001 or model.name=resnet*."
+    ))]
+    InvalidFilter { filter: String },
+    #[snafu(display("No job matching {id} has overrides matching all --where filters."))]
+    NoJobMatchesFilters { id: String },
+    #[snafu(display(
+        "Could not find a multirun directory in {} or any of its parents, pass one with --root.",
+        start.display()
+    ))]
+    MissingRoot { start: PathBuf },
+    #[snafu(display("Could not write job index {}.", path.display()))]
+    IndexWrite { source: io::Error, path: PathBuf },
+    #[snafu(display("Invalid job id {id}, expected e.g. 12345, 12345_3 or 12345_[0-7]."))]
+    InvalidJobId { id: String },
+    #[snafu(display("Unknown job id {id}.{}", did_you_mean(suggestions)))]
+    UnknownJobId {
+        id: String,
+        suggestions: Vec<String>,
+    },
+    #[snafu(display("Invalid regex {pattern}: {source}"))]
+    InvalidRegex {
+        source: regex::Error,
+        pattern: String,
+    },
+    #[snafu(display("Could not run {tool}, is Slurm installed and on the PATH?"))]
+    MissingSlurmTool { source: io::Error, tool: String },
+    #[snafu(display("Could not start the search threads: {source}"))]
+    ThreadPool { source: rayon::ThreadPoolBuildError },
+    #[snafu(display(
+        "The pattern {pattern} has no named groups like (?<loss>[0-9.]+) to extract metrics with."
+    ))]
+    NoMetricGroups { pattern: String },
+    #[snafu(display("There is no metric {name}, metrics are the named groups of the pattern."))]
+    UnknownMetric { name: String },
+    #[snafu(display("Terminal error: {source}"))]
+    Terminal { source: io::Error },
+    #[snafu(display("Could not write output: {source}"))]
+    Output { source: io::Error },
+    #[snafu(display("Could not decode {}: {reason}", path.display()))]
+    InvalidPickle { path: PathBuf, reason: String },
+    #[snafu(display("Could not find the submission script of the job in {}.", dir.display()))]
+    SubmissionScriptNotFound { dir: PathBuf },
+    #[snafu(display(
+        "Could not tell which script launched {}, pass it with --script.",
+        sweep_dir.display()
+    ))]
+    UnknownScript { sweep_dir: PathBuf },
+    #[snafu(display("Could not run {command}: {source}"))]
+    Resubmit { source: io::Error, command: String },
+    #[snafu(display("{command} failed with {status}."))]
+    ResubmitFailed { command: String, status: ExitStatus },
+    #[snafu(display("scancel could not cancel all of {jobs}, some may have finished already."))]
+    CancelFailed { jobs: String },
 }
 
-type PResult<T> = Result<T, ProgramError>;
-
-fn get_active_slurm_jobs() -> Vec<String> {
-    let mut cmd = ProcCommand::new("squeue");
-    cmd.arg("-h");
-    cmd.arg("-o");
-    cmd.arg("%i");
-    cmd.arg("--me");
-    cmd.arg("-t");
-    cmd.arg("RUNNING");
-    let output = cmd.output().unwrap();
-    if !output.status.success() {
-        return Vec::new();
+fn did_you_mean(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(" Did you mean {}?", suggestions.join(", "))
     }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let job_ids = stdout
-        .lines()
-        .map(|line| line.trim().to_string())
-        .filter(|line| !line.is_empty())
-        .collect();
-
-    job_ids
 }
 
+pub(crate) type PResult<T> = Result<T, ProgramError>;
+
 #[derive(Parser)]
 struct Cli {
+    /// Hydra multirun directories to read jobs from (can be given multiple
+    /// times). Defaults to the closest multirun/ and outputs/ directories
+    /// found walking up from the current directory.
+    #[arg(long, global = true, env = "VIEWLOGS_ROOT", value_delimiter = ':')]
+    root: Vec<PathBuf>,
+    /// Walk the multirun directories instead of using the job index
+    #[arg(long, global = true, default_value_t = false)]
+    no_cache: bool,
     #[clap(subcommand)]
     command: Command,
 }
 
+// Options shared by the subcommands that work on many jobs.
+#[derive(Args, Debug)]
+struct JobFilter {
+    /// Only consider jobs of this sweep, either "latest" or its directory
+    /// like 2024-05-01/12-30-00. A plain date picks all sweeps of that day.
+    #[arg(long)]
+    sweep: Option<SweepSelector>,
+    /// Only consider jobs whose Hydra overrides match KEY=VALUE, the value
+    /// may contain * and ? wildcards. Can be given multiple times.
+    #[arg(long = "where", value_name = "KEY=VALUE")]
+    overrides: Vec<OverrideFilter>,
+}
+
+impl JobFilter {
+    // Removes all jobs outside of the selected sweep. This happens before
+    // any other filter, so the latest sweep is the latest of all.
+    fn retain_sweep(&self, job_map: &mut BTreeMap<JobId, Job>) {
+        let selected = match &self.sweep {
+            None => return,
+            Some(SweepSelector::Name(name)) => name.clone(),
+            Some(SweepSelector::Latest) => {
+                let Some(latest) = job_map
+                    .values()
+                    .filter_map(|job| Some((job.sweep_time(), job.sweep_name()?)))
+                    .max()
+                else {
+                    return;
+                };
+                latest.1
+            }
+        };
+        job_map.retain(|_, job| {
+            job.sweep_name()
+                .is_some_and(|name| sweep::sweep_name_matches(&selected, &name))
+        });
+    }
+
+    fn matches(&self, overrides: &[String]) -> bool {
+        self.overrides.iter().all(|f| f.matches(overrides))
+    }
+
+    // Keeps the jobs whose overrides match. Jobs without readable Hydra
+    // overrides never match a filter.
+    fn apply<'a>(
+        &self,
+        jobs: Vec<(&'a JobId, &'a Job)>,
+        ws: &Workspace,
+    ) -> Vec<(&'a JobId, &'a Job)> {
+        if self.overrides.is_empty() {
+            return jobs;
+        }
+        // Reading the overrides of every job is slow on network file systems,
+        // so this is done in parallel.
+        jobs.into_par_iter()
+            .filter(|(id, job)| ws.job_overrides(id, job).is_ok_and(|o| self.matches(&o)))
+            .collect()
+    }
+}
+
 #[derive(Parser, Debug)]
 struct ViewOpts {
-    jobid: String,
+    /// Job to show, e.g. 12345_3. A bare job id like 12345 or a range like
+    /// 12345_[0-7] shows all matching array tasks.
+    jobid: JobSelector,
+    /// Keep streaming new lines from the .out and .err logs until the job
+    /// leaves the Slurm queue
+    #[arg(short, long, default_value_t = false)]
+    follow: bool,
+    /// Show the Hydra config and overrides of the job instead of its logs
+    #[arg(long, default_value_t = false, conflicts_with = "follow")]
+    config: bool,
+    /// Show the resources requested in the sbatch script and the function
+    /// submitit ran, instead of the logs
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["follow", "config", "traceback", "head", "tail", "format"]
+    )]
+    submission: bool,
+    /// Only show the Python traceback from the .err log, including chained
+    /// exceptions. --traceback=all shows every traceback in the last 8 MB of
+    /// the log instead of the last one.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHICH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "last",
+        conflicts_with_all = ["follow", "config"]
+    )]
+    traceback: Option<TracebackScope>,
+    /// Only print the first N lines of each log
+    #[arg(long, value_name = "N", conflicts_with_all = ["tail", "follow", "config", "traceback"])]
+    head: Option<usize>,
+    /// Only print the last N lines of each log
+    #[arg(long, value_name = "N", conflicts_with_all = ["follow", "config", "traceback"])]
+    tail: Option<usize>,
+    /// Print to stdout instead of through $PAGER
+    #[arg(long, default_value_t = false)]
+    no_pager: bool,
+    /// Print the log lines with their job, log and line number in a machine
+    /// readable format
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        conflicts_with_all = ["follow", "config", "traceback"]
+    )]
+    format: OutputFormat,
+    #[command(flatten)]
+    filter: JobFilter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum TracebackScope {
+    Last,
+    All,
+}
+
+#[derive(Parser, Debug)]
+struct ConfigOpts {
+    /// Job to show the config of, selected like in view
+    jobid: JobSelector,
+    /// Also print the Hydra runtime config (.hydra/hydra.yaml)
+    #[arg(long, default_value_t = false)]
+    hydra: bool,
 }
 
 #[derive(Parser, Debug)]
 struct SearchOpts {
     pattern: String,
+    #[command(flatten)]
+    filter: JobFilter,
     #[arg(long, default_value_t = false)]
     ids: bool,
     #[arg(long, default_value_t = false)]
     active: bool,
+    /// Cancel the jobs with matching logs with scancel, after asking for
+    /// confirmation
+    #[arg(long, default_value_t = false, requires = "active")]
+    cancel: bool,
+    /// Do not ask before cancelling
+    #[arg(short, long, default_value_t = false, requires = "cancel")]
+    yes: bool,
+    /// Only search jobs in these Slurm states, e.g. --state FAILED,TIMEOUT
+    #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+    state: Vec<JobState>,
+    /// Which log streams to search
+    #[arg(long, value_enum, default_value_t = Stream::Both)]
+    stream: Stream,
+    /// Number of logs to scan in parallel, defaults to the number of CPUs
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Print NUM lines of trailing context after each match
+    #[arg(short = 'A', long, value_name = "NUM")]
+    after_context: Option<usize>,
+    /// Print NUM lines of leading context before each match
+    #[arg(short = 'B', long, value_name = "NUM")]
+    before_context: Option<usize>,
+    /// Print NUM lines of context around each match
+    #[arg(short = 'C', long, value_name = "NUM")]
+    context: Option<usize>,
+    /// Prefix each line with its line number in the log
+    #[arg(short = 'n', long, default_value_t = false)]
+    line_number: bool,
+    /// Only print the number of matching lines per job
+    #[arg(short = 'c', long, default_value_t = false)]
+    count: bool,
+    /// Stop reading a log after NUM matching lines
+    #[arg(short = 'm', long, value_name = "NUM")]
+    max_count: Option<usize>,
+    /// Only print the paths of logs with at least one match
+    #[arg(short = 'l', long, default_value_t = false)]
+    files_with_matches: bool,
+    /// Print the matching lines (or ids, paths, counts) with their job, log
+    /// and the byte offsets of the matches in a machine readable format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+impl SearchOpts {
+    // Lines of (before, after) context, -A and -B take precedence over -C.
+    fn context_sizes(&self) -> (usize, usize) {
+        if self.count || self.ids || self.files_with_matches {
+            return (0, 0);
+        }
+        let context = self.context.unwrap_or(0);
+        (
+            self.before_context.unwrap_or(context),
+            self.after_context.unwrap_or(context),
+        )
+    }
+
+    // Counting and listing ids or files needs no lines.
+    fn prints_lines(&self) -> bool {
+        !(self.count || self.ids || self.files_with_matches)
+    }
+
+    // Listing ids or files only needs to know whether a log matches at all.
+    fn effective_max_count(&self) -> Option<usize> {
+        if self.ids || self.files_with_matches {
+            Some(1)
+        } else {
+            self.max_count
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Stream {
+    Out,
+    Err,
+    Both,
+}
+
+impl Stream {
+    fn endings(self) -> &'static [&'static str] {
+        match self {
+            Stream::Out => &["out"],
+            Stream::Err => &["err"],
+            Stream::Both => &["out", "err"],
+        }
+    }
+}
+
+// Colored tag that marks which log stream a line came from.
+fn stream_tag(ending: &str) -> String {
+    let tag = format!("[{ending}]");
+    match ending {
+        "err" => tag.yellow().to_string(),
+        _ => tag.cyan().to_string(),
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -66,14 +370,187 @@ enum Command {
     /// The ID of the job we want to find
     View(ViewOpts),
     Search(SearchOpts),
+    /// Rebuild the job index of every root from scratch
+    Reindex,
+    /// Print a table of all known jobs
+    List(ListOpts),
+    /// Show the Hydra config and overrides a job was run with
+    Config(ConfigOpts),
+    /// Print a summary of every sweep (multirun launch)
+    Sweeps(SweepsOpts),
+    /// Find out why jobs failed from their stderr logs
+    Failures(FailuresOpts),
+    /// Extract metrics like the loss from the .out logs of all jobs
+    Metrics(MetricsOpts),
+    /// Compare the resources jobs requested with what they used, like seff
+    Efficiency(EfficiencyOpts),
+    /// Print or run the Hydra commands that launch jobs again with their
+    /// original overrides
+    Resubmit(ResubmitOpts),
+    /// Cancel Slurm jobs with scancel
+    Cancel(CancelOpts),
+    /// Browse all jobs and their logs interactively
+    Tui,
+}
+
+#[derive(Parser, Debug)]
+struct FailuresOpts {
+    #[command(flatten)]
+    filter: JobFilter,
+}
+
+#[derive(Parser, Debug)]
+struct MetricsOpts {
+    /// Regex whose named groups are extracted as metrics from every matching
+    /// line, e.g. 'loss (?<loss>[0-9.]+) \| acc (?<acc>[0-9.]+)'
+    pattern: String,
+    #[command(flatten)]
+    filter: JobFilter,
+    /// Print every extracted value instead of a summary per job
+    #[arg(long, default_value_t = false)]
+    series: bool,
+    /// Statistics to summarize each metric with
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "last,best")]
+    stats: Vec<Stat>,
+    /// Metrics for which higher values are better, like an accuracy. Lower
+    /// is better for all others.
+    #[arg(long, value_delimiter = ',', value_name = "METRIC")]
+    maximize: Vec<String>,
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Draw a line plot of this metric over the steps of every job instead of
+    /// printing a table
+    #[arg(long, value_name = "METRIC", conflicts_with_all = ["series", "format"])]
+    plot: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+struct EfficiencyOpts {
+    /// Jobs to report on, selected like in view. Defaults to all jobs.
+    jobid: Option<JobSelector>,
+    #[command(flatten)]
+    filter: JobFilter,
+    /// Flag completed jobs that requested at least FACTOR times the memory
+    /// or time they used
+    #[arg(long, value_name = "FACTOR", default_value_t = 4.0)]
+    flag_factor: f64,
+    /// Only list flagged jobs, the per sweep summary still covers all
+    /// completed jobs
+    #[arg(long, default_value_t = false)]
+    flagged: bool,
+}
+
+#[derive(Parser, Debug)]
+struct ResubmitOpts {
+    /// Jobs to resubmit, selected like in view. Defaults to all jobs.
+    jobid: Option<JobSelector>,
+    #[command(flatten)]
+    filter: JobFilter,
+    /// Only resubmit jobs that failed
+    #[arg(long, default_value_t = false)]
+    failed: bool,
+    /// Script that launched the sweep. Defaults to <hydra.job.name>.py in
+    /// the directory the sweep was launched from.
+    #[arg(long)]
+    script: Option<String>,
+    #[arg(long, default_value = "python")]
+    python: String,
+    /// Launch one sweep per job, instead of a single sweep over all jobs of
+    /// a sweep where that does not launch extra jobs
+    #[arg(long, default_value_t = false)]
+    per_job: bool,
+    /// Run the commands instead of only printing them
+    #[arg(long, default_value_t = false)]
+    execute: bool,
 }
 
-fn get_subdirectories<P: AsRef<Path>>(start: P) -> PResult<Vec<PathBuf>> {
+#[derive(Parser, Debug)]
+struct CancelOpts {
+    /// Jobs to cancel, e.g. 12345 for all tasks of a job array, 12345_3 or
+    /// 12345_[0-7]
+    #[arg(required = true)]
+    jobids: Vec<JobSelector>,
+    /// Do not ask before cancelling
+    #[arg(short, long, default_value_t = false)]
+    yes: bool,
+}
+
+#[derive(Parser, Debug)]
+struct SweepsOpts {
+    /// Only list the N most recent sweeps
+    #[arg(long)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ListSort {
+    /// Most recently modified logs first
+    Time,
+    /// Highest job id first
+    Id,
+    /// Largest logs first
+    Size,
+}
+
+#[derive(Parser, Debug)]
+struct ListOpts {
+    #[arg(long, value_enum, default_value_t = ListSort::Id)]
+    sort: ListSort,
+    #[command(flatten)]
+    filter: JobFilter,
+    /// Only list sweeps launched at or after this date, e.g. 2024-05-01 or
+    /// 2024-05-01/12-30-00
+    #[arg(long, value_parser = parse_since)]
+    since: Option<NaiveDateTime>,
+    /// Only list sweeps launched at or before this date, a plain date
+    /// includes the whole day
+    #[arg(long, value_parser = parse_until)]
+    until: Option<NaiveDateTime>,
+    /// Only list the first N jobs
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Only list jobs in these Slurm states, e.g. --state FAILED,TIMEOUT
+    #[arg(long, value_enum, value_delimiter = ',', ignore_case = true)]
+    state: Vec<JobState>,
+    /// Print exact sizes, times and states in a machine readable format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+const DATE_TIME_FORMATS: [&str; 3] = [
+    "%Y-%m-%d/%H-%M-%S",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+fn parse_date_bound(s: &str, end_of_day: bool) -> Result<NaiveDateTime, String> {
+    for format in DATE_TIME_FORMATS {
+        if let Ok(date_time) = NaiveDateTime::parse_from_str(s, format) {
+            return Ok(date_time);
+        }
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| format!("expected a date like 2024-05-01 or 2024-05-01/12-30-00, got {s}"))?;
+    Ok(if end_of_day {
+        date.and_hms_opt(23, 59, 59).unwrap()
+    } else {
+        date.and_hms_opt(0, 0, 0).unwrap()
+    })
+}
+
+fn parse_since(s: &str) -> Result<NaiveDateTime, String> {
+    parse_date_bound(s, false)
+}
+
+fn parse_until(s: &str) -> Result<NaiveDateTime, String> {
+    parse_date_bound(s, true)
+}
+
+pub(crate) fn get_subdirectories<P: AsRef<Path>>(start: P) -> PResult<Vec<PathBuf>> {
     Ok(fs::read_dir(&start)
         .context(FileNotFoundSnafu {
             path: start.as_ref().to_path_buf(),
         })?
-        .into_iter()
         .filter_map(|entry| {
             let entry = entry.ok()?;
             let file_type = entry.file_type().ok()?;
@@ -82,41 +559,220 @@ fn get_subdirectories<P: AsRef<Path>>(start: P) -> PResult<Vec<PathBuf>> {
         .collect())
 }
 
-// Multirun dictionaries from submitit.slurm have the following structure:
-// multirun/YYYY-MM-DD/hh-mm-ss/.submitit/<job-id>_<arr_id>
-// We can do the following:
-//   1. Flatten the nested datetime structs
-//   2. Find all job ids and make a map: (job_id,path_to_job_id_dir)
-//   3. Use job + arr id to find the correct job
-fn build_job_map<P: AsRef<Path>>(start: P) -> PResult<HashMap<String, PathBuf>> {
-    let mut jobmap = HashMap::new();
-    let start = start.as_ref();
-
-    for ymd in get_subdirectories(start)? {
-        for hms in get_subdirectories(ymd)? {
-            let submitit_dir = hms.join(".submitit");
-            if !submitit_dir.exists() {
-                continue;
-            }
-            for job in get_subdirectories(submitit_dir)? {
-                if let Some(name) = job.file_name() {
-                    jobmap.insert(name.to_str().unwrap().to_string(), job);
-                }
+// Walks up from the current directory and returns the Hydra output
+// directories of the first ancestor that has any. The returned paths are
+// relative to the current directory, so headers stay short.
+fn discover_roots() -> PResult<Vec<PathBuf>> {
+    let cwd = std::env::current_dir().context(FileNotFoundSnafu {
+        path: PathBuf::from("."),
+    })?;
+    for (levels_up, dir) in cwd.ancestors().enumerate() {
+        let roots: Vec<PathBuf> = ROOT_DIR_NAMES
+            .iter()
+            .filter(|name| dir.join(name).is_dir())
+            .map(|name| {
+                let mut rel: PathBuf = std::iter::repeat_n("..", levels_up).collect();
+                rel.push(name);
+                rel
+            })
+            .collect();
+        if !roots.is_empty() {
+            return Ok(roots);
+        }
+    }
+    Err(MissingRootSnafu { start: cwd }.build())
+}
+
+fn resolve_roots(roots: Vec<PathBuf>) -> PResult<Vec<PathBuf>> {
+    if roots.is_empty() {
+        discover_roots()
+    } else {
+        Ok(roots)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Job {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl Job {
+    // The sweep directory, i.e. the YYYY-MM-DD/hh-mm-ss part of
+    // <root>/YYYY-MM-DD/hh-mm-ss/.submitit/<job>.
+    fn sweep_dir(&self) -> Option<&Path> {
+        self.dir.parent()?.parent()
+    }
+
+    // Name of the sweep the job belongs to, e.g. 2024-05-01/12-30-00.
+    fn sweep_name(&self) -> Option<String> {
+        let hms = self.sweep_dir()?;
+        let ymd = hms.parent()?;
+        Some(format!(
+            "{}/{}",
+            ymd.file_name()?.to_str()?,
+            hms.file_name()?.to_str()?
+        ))
+    }
+
+    // Launch time of the sweep as encoded in the sweep directory.
+    fn sweep_time(&self) -> Option<NaiveDateTime> {
+        NaiveDateTime::parse_from_str(&self.sweep_name()?, DATE_TIME_FORMATS[0]).ok()
+    }
+
+    fn log_metadata(&self, ending: &str) -> Option<fs::Metadata> {
+        fs::metadata(get_log_pathbuf(&self.dir, ending).ok()?).ok()
+    }
+}
+
+// Where to look for jobs and how, shared by all subcommands.
+struct Workspace {
+    roots: Vec<PathBuf>,
+    use_cache: bool,
+    job_dirs: hydra::JobDirs,
+}
+
+impl Workspace {
+    // Multirun dictionaries from submitit.slurm have the following structure:
+    // multirun/YYYY-MM-DD/hh-mm-ss/.submitit/<job-id>_<arr_id>
+    // We can do the following:
+    //   1. Flatten the nested datetime structs
+    //   2. Find all job ids and make a map: (job_id,path_to_job_id_dir)
+    //   3. Use job + arr id to find the correct job
+    // If several roots are given, their maps are merged. Should a job id appear
+    // under more than one root, the root given first wins.
+    // Directories that are not named like a Slurm job id are skipped.
+    fn build_job_map(&self) -> PResult<BTreeMap<JobId, Job>> {
+        let mut jobmap = BTreeMap::new();
+
+        for root in &self.roots {
+            for job in index::root_job_dirs(root, self.use_cache)? {
+                let Some(id) = job
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(|name| name.parse().ok())
+                else {
+                    continue;
+                };
+                jobmap.entry(id).or_insert_with(|| Job {
+                    root: root.clone(),
+                    dir: job,
+                });
             }
         }
+        Ok(jobmap)
+    }
+
+    fn hydra_job_dir(&self, id: &JobId, job: &Job) -> PResult<PathBuf> {
+        let sweep_dir = job.sweep_dir().unwrap_or(&job.dir);
+        self.job_dirs.find(sweep_dir, id)
+    }
+
+    fn job_overrides(&self, id: &JobId, job: &Job) -> PResult<Vec<String>> {
+        hydra::read_overrides(&self.hydra_job_dir(id, job)?)
     }
-    Ok(jobmap)
+
+    // Prefixes a header with the root the job was found in, which is only
+    // worth the noise when jobs from several roots are mixed.
+    fn with_root(&self, header: String, job: &Job) -> String {
+        if self.roots.len() > 1 {
+            format!("[{}] {header}", job.root.display())
+        } else {
+            header
+        }
+    }
+}
+
+// Which part of a log view prints.
+#[derive(Debug, Clone, Copy)]
+enum LogPart {
+    All,
+    Head(usize),
+    Tail(usize),
 }
 
-fn get_log_content_or_error_msg<P: AsRef<Path>>(dir: P, ending: &str) -> String {
+fn get_log_content_or_error_msg<P: AsRef<Path>>(dir: P, ending: &str, part: LogPart) -> String {
     let log_fp = get_log_pathbuf(dir, ending);
     if log_fp.is_err() {
         return log_fp.err().unwrap().to_string();
     }
-    let log_content = get_log_content(log_fp.unwrap());
+    let log_content = match part {
+        LogPart::All => get_log_content(log_fp.unwrap()),
+        LogPart::Head(lines) => get_log_first_lines(log_fp.unwrap(), lines),
+        LogPart::Tail(lines) => get_log_last_lines(log_fp.unwrap(), lines),
+    };
     log_content.unwrap_or("Could not read log.".to_string())
 }
 
+fn get_log_first_lines<P: AsRef<Path>>(filepath: P, lines: usize) -> Option<String> {
+    let mut reader = BufReader::new(File::open(filepath).ok()?);
+    let mut buf = Vec::new();
+    for _ in 0..lines {
+        if reader.read_until(b'\n', &mut buf).ok()? == 0 {
+            break;
+        }
+    }
+    Some(
+        String::from_utf8_lossy(&buf)
+            .trim_end_matches('\n')
+            .to_string(),
+    )
+}
+
+// Reads the log backwards in chunks until enough lines are found, so that
+// only the end of huge logs is ever read. Returns the offset the last lines
+// start at.
+fn last_lines_start(file: &mut File, lines: usize) -> Option<u64> {
+    const CHUNK_SIZE: u64 = 64 * 1024;
+    let len = file.metadata().ok()?.len();
+    if lines == 0 {
+        return Some(len);
+    }
+    let mut found = 0;
+    let mut start = len;
+    let mut chunk = Vec::new();
+    while start > 0 {
+        let chunk_start = start.saturating_sub(CHUNK_SIZE);
+        chunk.resize((start - chunk_start) as usize, 0);
+        file.seek(SeekFrom::Start(chunk_start)).ok()?;
+        file.read_exact(&mut chunk).ok()?;
+        for (i, &b) in chunk.iter().enumerate().rev() {
+            let offset = chunk_start + i as u64;
+            // A trailing newline does not start another line.
+            if b == b'\n' && offset + 1 != len {
+                found += 1;
+                if found == lines {
+                    return Some(offset + 1);
+                }
+            }
+        }
+        start = chunk_start;
+    }
+    Some(0)
+}
+
+fn get_log_last_lines<P: AsRef<Path>>(filepath: P, lines: usize) -> Option<String> {
+    let mut file = File::open(filepath).ok()?;
+    let start = last_lines_start(&mut file, lines)?;
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    let content = buf.strip_suffix(b"\n").unwrap_or(&buf);
+    Some(String::from_utf8_lossy(content).to_string())
+}
+
+// Reads at most the last max_bytes of a log, which is where submitit and
+// Python put the interesting bits when a job ends.
+fn get_log_tail<P: AsRef<Path>>(filepath: P, max_bytes: u64) -> Option<String> {
+    let mut file = File::open(filepath).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))
+        .ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).to_string())
+}
+
 fn get_log_content<P: AsRef<Path>>(filepath: P) -> Option<String> {
     let mut file = File::open(filepath).ok()?;
     let mut contents = String::new();
@@ -124,6 +780,13 @@ fn get_log_content<P: AsRef<Path>>(filepath: P) -> Option<String> {
     Some(contents)
 }
 
+// Like get_log_content, but invalid UTF-8, e.g. a progress bar cut off in
+// the middle of a character, is replaced instead of failing the read.
+fn get_log_content_lossy<P: AsRef<Path>>(filepath: P) -> Option<String> {
+    let contents = fs::read(filepath).ok()?;
+    Some(String::from_utf8_lossy(&contents).into_owned())
+}
+
 fn get_log_pathbuf<P: AsRef<Path>>(dir: P, ending: &str) -> PResult<PathBuf> {
     let dir = dir.as_ref();
     for entry in fs::read_dir(dir).context(FileNotFoundSnafu {
@@ -132,8 +795,8 @@ fn get_log_pathbuf<P: AsRef<Path>>(dir: P, ending: &str) -> PResult<PathBuf> {
         let f = entry.context(FileNotFoundSnafu {
             path: dir.to_path_buf(),
         })?;
-        if f.file_type().unwrap().is_file()
-            && f.path().extension().map_or(false, |ext| ext == ending)
+        if f.file_type().is_ok_and(|file_type| file_type.is_file())
+            && f.path().extension().is_some_and(|ext| ext == ending)
         {
             return Ok(f.path());
         }
@@ -145,76 +808,1863 @@ fn get_log_pathbuf<P: AsRef<Path>>(dir: P, ending: &str) -> PResult<PathBuf> {
     .build())
 }
 
-fn view(v: ViewOpts) {
-    let target = v.jobid;
-    let job_map = build_job_map("multirun").unwrap();
-    let job_path = job_map[&target].clone();
-    for ending in ["out", "err"] {
-        let header = format!("Reporting {ending} file for job at {:?}:", job_path);
+// Keeps track of how far a log stream has been read, so that polling it
+// only yields the lines appended since the last poll.
+struct LogFollower {
+    // Shown in front of every line when several jobs are followed at once.
+    label: Option<String>,
+    dir: PathBuf,
+    ending: &'static str,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl LogFollower {
+    fn new(label: Option<String>, dir: PathBuf, ending: &'static str) -> Self {
+        LogFollower {
+            label,
+            dir,
+            ending,
+            offset: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    fn prefix(&self) -> String {
+        match &self.label {
+            Some(label) => format!("{} {}", label.bold(), stream_tag(self.ending)),
+            None => stream_tag(self.ending),
+        }
+    }
+
+    // Skips all but the last lines of the log, but at most max_bytes of it,
+    // so that huge logs are not read in full. Returns whether anything was
+    // skipped.
+    fn skip_to_tail(&mut self, lines: usize, max_bytes: u64) -> bool {
+        let Ok(log_fp) = get_log_pathbuf(&self.dir, self.ending) else {
+            return false;
+        };
+        let Ok(mut file) = File::open(log_fp) else {
+            return false;
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        let start = last_lines_start(&mut file, lines).unwrap_or(0);
+        self.offset = start.max(len.saturating_sub(max_bytes));
+        self.partial.clear();
+        self.offset > 0
+    }
+
+    // Returns all complete lines appended since the last call. A log that
+    // does not exist yet (e.g. the job is still pending) yields no lines.
+    fn poll(&mut self) -> Vec<String> {
+        let Ok(log_fp) = get_log_pathbuf(&self.dir, self.ending) else {
+            return Vec::new();
+        };
+        let Ok(mut file) = File::open(log_fp) else {
+            return Vec::new();
+        };
+        let len = file.metadata().map(|m| m.len()).unwrap_or(0);
+        if len < self.offset {
+            // The log was truncated or replaced, start over.
+            self.offset = 0;
+            self.partial.clear();
+        }
+        if file.seek(SeekFrom::Start(self.offset)).is_err() {
+            return Vec::new();
+        }
+        let mut buf = Vec::new();
+        let Ok(read) = file.read_to_end(&mut buf) else {
+            return Vec::new();
+        };
+        self.offset += read as u64;
+        self.partial.extend_from_slice(&buf);
+
+        let Some(last_newline) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(last_newline + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    // Returns a trailing line that was never terminated by a newline.
+    fn flush(&mut self) -> Option<String> {
+        if self.partial.is_empty() {
+            return None;
+        }
+        let line = String::from_utf8_lossy(&self.partial).to_string();
+        self.partial.clear();
+        Some(line)
+    }
+}
+
+fn write_follow_headers(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    ws: &Workspace,
+) -> io::Result<()> {
+    for (_, job) in jobs {
+        let header = ws.with_root(
+            format!("Following out and err files for job at {:?}:", job.dir),
+            job,
+        );
         let dashes = "-".repeat(header.len());
+        writeln!(out, "{}\n{dashes}", header.bold())?;
+    }
+    Ok(())
+}
 
-        println!(
-            "{}\n{}\n{}\n",
-            header.bold(),
-            dashes.clone(),
-            get_log_content_or_error_msg(job_path.clone(), ending)
+fn write_new_lines(out: &mut dyn Write, followers: &mut [LogFollower]) -> io::Result<()> {
+    for follower in followers {
+        let prefix = follower.prefix();
+        for line in follower.poll() {
+            writeln!(out, "{prefix} {line}")?;
+        }
+    }
+    Ok(())
+}
+
+// All jobs share the same Slurm job id, as they were picked by a single
+// selector, so one squeue call per poll suffices. Following stops quietly
+// once the output is closed, e.g. by head.
+fn follow(out: &mut dyn Write, job: u64, jobs: &[(&JobId, &Job)], ws: &Workspace) -> PResult<()> {
+    let mut followers: Vec<LogFollower> = jobs
+        .iter()
+        .flat_map(|(id, job)| {
+            let label = (jobs.len() > 1).then(|| id.to_string());
+            ["out", "err"].map(|ending| LogFollower::new(label.clone(), job.dir.clone(), ending))
+        })
+        .collect();
+    let mut result = write_follow_headers(out, jobs, ws);
+    while result.is_ok() {
+        // Query the queue before reading, so lines written right before the
+        // job finishes are still picked up by the final poll.
+        let queued = slurm::get_queued_tasks(job)?;
+        let alive = jobs.iter().any(|(id, _)| queued.contains(id));
+        result = write_new_lines(out, &mut followers);
+        if !alive {
+            break;
+        }
+        thread::sleep(FOLLOW_POLL_INTERVAL);
+    }
+    let result = result.and_then(|()| {
+        for follower in followers.iter_mut() {
+            if let Some(line) = follower.flush() {
+                writeln!(out, "{} {line}", follower.prefix())?;
+            }
+        }
+        writeln!(out, "{}", "No followed job is in the queue anymore.".bold())
+    });
+    ignore_broken_pipe(result)
+}
+
+// Slurm's view of the given jobs. Filtering by state needs Slurm, for
+// display purposes missing Slurm tooling just leaves the state unknown.
+fn get_job_infos<'a>(
+    ids: impl IntoIterator<Item = &'a JobId>,
+    required: bool,
+) -> PResult<HashMap<JobId, JobInfo>> {
+    match slurm::get_job_infos(ids) {
+        Err(e) if required => Err(e),
+        result => Ok(result.unwrap_or_default()),
+    }
+}
+
+fn matches_state(states: &[JobState], info: Option<&JobInfo>) -> bool {
+    states.is_empty() || info.is_some_and(|info| states.contains(&info.state))
+}
+
+fn select_jobs<'a>(
+    target: &JobSelector,
+    job_map: &'a BTreeMap<JobId, Job>,
+) -> PResult<Vec<(&'a JobId, &'a Job)>> {
+    let jobs: Vec<_> = job_map
+        .iter()
+        .filter(|(id, _)| target.matches(id))
+        .collect();
+    if jobs.is_empty() {
+        return Err(UnknownJobIdSnafu {
+            id: target.to_string(),
+            suggestions: target.suggestions(job_map.keys()),
+        }
+        .build());
+    }
+    Ok(jobs)
+}
+
+fn format_config(id: &JobId, job: &Job, with_hydra: bool, ws: &Workspace) -> PResult<String> {
+    let hydra_job_dir = ws.hydra_job_dir(id, job)?;
+    let mut report = format!(
+        "Hydra job directory: {}\n\nOverrides:\n",
+        hydra_job_dir.display()
+    );
+    for o in hydra::read_overrides(&hydra_job_dir)? {
+        report.push_str(&format!("  {o}\n"));
+    }
+    report.push_str("\nConfig:\n");
+    report.push_str(&hydra::read_hydra_file(&hydra_job_dir, "config.yaml")?);
+    if with_hydra {
+        report.push_str("\nHydra config:\n");
+        report.push_str(&hydra::read_hydra_file(&hydra_job_dir, "hydra.yaml")?);
+    }
+    Ok(report)
+}
+
+fn print_configs(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    with_hydra: bool,
+    ws: &Workspace,
+) -> io::Result<()> {
+    for (id, job) in jobs {
+        let header = ws.with_root(
+            format!("Reporting Hydra config for job at {:?}:", job.dir),
+            job,
+        );
+        let dashes = "-".repeat(header.len());
+        let report = format_config(id, job, with_hydra, ws).unwrap_or_else(|e| e.to_string());
+        writeln!(out, "{}\n{dashes}\n{report}\n", header.bold())?;
+    }
+    Ok(())
+}
+
+fn config(c: ConfigOpts, ws: &Workspace) -> PResult<()> {
+    let job_map = ws.build_job_map()?;
+    let jobs = select_jobs(&c.jobid, &job_map)?;
+    ignore_broken_pipe(print_configs(&mut io::stdout(), &jobs, c.hydra, ws))
+}
+
+// A frame line of a Python traceback, e.g.
+//   File "/home/user/train.py", line 42, in main
+static TRACEBACK_FRAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^(\s*File ")(.*)(", line )(\d+)(, in )(.*)$"#).unwrap());
+
+fn highlight_traceback_line(line: &str) -> String {
+    if let Some(caps) = TRACEBACK_FRAME.captures(line) {
+        return format!(
+            "{}{}{}{}{}{}",
+            &caps[1],
+            caps[2].cyan(),
+            &caps[3],
+            caps[4].yellow(),
+            &caps[5],
+            caps[6].green()
+        );
+    }
+    if line.is_empty() || line.starts_with(char::is_whitespace) {
+        return line.to_string();
+    }
+    // Headers, chain markers and the exception lines are the only unindented
+    // lines of a traceback.
+    if line.ends_with(':') && !line.contains(": ") {
+        line.bold().to_string()
+    } else {
+        line.red().bold().to_string()
+    }
+}
+
+// The last traceback is looked for where failures looks for it. Earlier ones
+// are only looked for in the last few MB, as err logs can get huge.
+const TRACEBACK_TAIL_BYTES: u64 = 8 * 1024 * 1024;
+
+fn format_tracebacks(job_path: &Path, scope: TracebackScope) -> PResult<String> {
+    let log_fp = get_log_pathbuf(job_path, "err")?;
+    let max_bytes = match scope {
+        TracebackScope::Last => FAILURE_TAIL_BYTES,
+        TracebackScope::All => TRACEBACK_TAIL_BYTES,
+    };
+    let Some(log) = get_log_tail(log_fp, max_bytes) else {
+        return Ok("Could not read log.".to_string());
+    };
+    let mut tracebacks = failures::extract_tracebacks(&log);
+    if scope == TracebackScope::Last && tracebacks.len() > 1 {
+        tracebacks.drain(..tracebacks.len() - 1);
+    }
+    if tracebacks.is_empty() {
+        return Ok("No traceback found.".to_string());
+    }
+    Ok(tracebacks
+        .iter()
+        .map(|block| {
+            block
+                .iter()
+                .map(|line| highlight_traceback_line(line))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}
+
+fn print_tracebacks(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    scope: TracebackScope,
+    ws: &Workspace,
+) -> io::Result<()> {
+    for (_, job) in jobs {
+        let header = ws.with_root(
+            format!("Reporting traceback for job at {:?}:", job.dir),
+            job,
+        );
+        let dashes = "-".repeat(header.len());
+        let report = format_tracebacks(&job.dir, scope).unwrap_or_else(|e| e.to_string());
+        writeln!(out, "{}\n{dashes}\n{report}\n", header.bold())?;
+    }
+    Ok(())
+}
+
+// $PAGER split into the program and its arguments, less by default.
+fn pager_command() -> ProcCommand {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less -R".to_string());
+    let mut args = pager.split_whitespace();
+    let mut command = ProcCommand::new(args.next().unwrap());
+    command.args(args);
+    command
+}
+
+// Like git, less quits right away if the output fits on one screen and
+// leaves it there, unless the user configured less otherwise.
+fn start_pager() -> Option<Child> {
+    let mut command = pager_command();
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+    command.stdin(Stdio::piped()).spawn().ok()
+}
+
+// Quitting the pager (or head) before all output is written is fine.
+fn ignore_broken_pipe(result: io::Result<()>) -> PResult<()> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => result.context(OutputSnafu),
+    }
+}
+
+fn print_logs(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    infos: &HashMap<JobId, JobInfo>,
+    part: LogPart,
+    ws: &Workspace,
+) -> io::Result<()> {
+    for (id, job) in jobs {
+        let job_path = &job.dir;
+        let state = infos
+            .get(id)
+            .map(|info| format!(" ({})", info.summary()))
+            .unwrap_or_default();
+        for ending in ["out", "err"] {
+            let header = ws.with_root(
+                format!("Reporting {ending} file for job at {:?}{state}:", job_path),
+                job,
+            );
+            let dashes = "-".repeat(header.len());
+
+            writeln!(
+                out,
+                "{}\n{}\n{}\n",
+                header.bold(),
+                dashes.clone(),
+                get_log_content_or_error_msg(job_path, ending, part)
+            )?;
+        }
+
+        let report = match submitit::read_result(job_path) {
+            Ok(None) => continue,
+            Ok(Some(result)) => format_job_result(result),
+            Err(e) => e.to_string(),
+        };
+        let header = ws.with_root(format!("Reporting result for job at {:?}:", job_path), job);
+        let dashes = "-".repeat(header.len());
+        writeln!(out, "{}\n{dashes}\n{report}\n", header.bold())?;
+    }
+    Ok(())
+}
+
+// Results can be arbitrarily large, e.g. a list with a value per step.
+const MAX_RESULT_CHARS: usize = 10_000;
+// Submitted arguments are shown one per line.
+const MAX_ARGUMENT_CHARS: usize = 200;
+
+fn truncate_chars(mut s: String, max_chars: usize) -> String {
+    if let Some((cut, _)) = s.char_indices().nth(max_chars) {
+        s.truncate(cut);
+        s.push_str("...");
+    }
+    s
+}
+
+fn format_job_result(result: submitit::JobResult) -> String {
+    match result {
+        submitit::JobResult::Success(value) => {
+            let value = truncate_chars(value.to_string(), MAX_RESULT_CHARS);
+            format!("{} {value}", "success:".green().bold())
+        }
+        submitit::JobResult::LargeSuccess(len) => format!(
+            "{} a {} result, too large to show",
+            "success:".green().bold(),
+            table::format_size(len)
+        ),
+        submitit::JobResult::Error(traceback) => {
+            let traceback: Vec<String> = traceback.lines().map(highlight_traceback_line).collect();
+            format!("{}\n{}", "error:".red().bold(), traceback.join("\n"))
+        }
+    }
+}
+
+fn format_sbatch_request(job_dir: &Path) -> PResult<String> {
+    let request = submitit::read_sbatch_request(job_dir)?;
+    let resources = [
+        ("job name", request.job_name),
+        ("partition", request.partition),
+        ("nodes", request.nodes),
+        ("tasks", request.tasks.map(|tasks| tasks.to_string())),
+        ("cpus", request.cpus.map(|cpus| cpus.to_string())),
+        ("gpus", request.gpus.map(|gpus| gpus.to_string())),
+        ("memory", request.mem.map(|mem| mem.to_string())),
+        ("time limit", request.time_limit.map(slurm::format_duration)),
+        ("array", request.array),
+    ];
+    let mut report = format!(
+        "Submission script: {}\n\nRequested resources:\n",
+        request.script.display()
+    );
+    for (name, value) in resources {
+        if let Some(value) = value {
+            report.push_str(&format!("  {name:<12}{value}\n"));
+        }
+    }
+    Ok(report)
+}
+
+fn format_submission(job_dir: &Path) -> PResult<String> {
+    let Some(submission) = submitit::read_submission(job_dir)? else {
+        return Ok("No submitted function found.".to_string());
+    };
+    let mut report = format!("Submitted function: {}\n", submission.function.green());
+    if !submission.args.is_empty() {
+        report.push_str("\nArguments:\n");
+        for arg in submission.args {
+            report.push_str(&format!(
+                "  {}\n",
+                truncate_chars(arg.to_string(), MAX_ARGUMENT_CHARS)
+            ));
+        }
+    }
+    if !submission.kwargs.is_empty() {
+        report.push_str("\nKeyword arguments:\n");
+        for (key, value) in submission.kwargs {
+            let key = match key {
+                pickle::PickleValue::Str(key) => key,
+                key => key.to_string(),
+            };
+            let value = truncate_chars(value.to_string(), MAX_ARGUMENT_CHARS);
+            report.push_str(&format!("  {key}={value}\n"));
+        }
+    }
+    Ok(report)
+}
+
+fn print_submissions(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    ws: &Workspace,
+) -> io::Result<()> {
+    for (_, job) in jobs {
+        let header = ws.with_root(
+            format!("Reporting submission for job at {:?}:", job.dir),
+            job,
         );
+        let dashes = "-".repeat(header.len());
+        let request = format_sbatch_request(&job.dir).unwrap_or_else(|e| e.to_string());
+        let submission = format_submission(&job.dir).unwrap_or_else(|e| e.to_string());
+        writeln!(
+            out,
+            "{}\n{dashes}\n{}\n\n{}\n",
+            header.bold(),
+            request.trim_end(),
+            submission.trim_end()
+        )?;
+    }
+    Ok(())
+}
+
+fn write_log_records(
+    out: &mut dyn Write,
+    jobs: &[(&JobId, &Job)],
+    format: RecordFormat,
+    part: LogPart,
+) -> io::Result<()> {
+    let mut writer = RecordWriter::new(format, &["job", "path", "stream", "line_number", "line"]);
+    for (id, job) in jobs {
+        for ending in ["out", "err"] {
+            let Ok(log_fp) = get_log_pathbuf(&job.dir, ending) else {
+                continue;
+            };
+            let content = match part {
+                LogPart::All => get_log_content_lossy(&log_fp),
+                LogPart::Head(lines) => get_log_first_lines(&log_fp, lines),
+                LogPart::Tail(lines) => get_log_last_lines(&log_fp, lines),
+            };
+            let Some(content) = content else {
+                continue;
+            };
+            for (i, line) in content.lines().enumerate() {
+                // The line numbers at the end of a log are unknown without
+                // reading all of it.
+                let line_number = match part {
+                    LogPart::Tail(_) => Value::Null,
+                    _ => (i + 1).into(),
+                };
+                writer.write(
+                    out,
+                    vec![
+                        id.to_string().into(),
+                        log_fp.to_string_lossy().into(),
+                        ending.into(),
+                        line_number,
+                        line.into(),
+                    ],
+                )?;
+            }
+        }
+    }
+    writer.finish(out)
+}
+
+fn view(v: ViewOpts, ws: &Workspace) -> PResult<()> {
+    let target = v.jobid;
+    let mut job_map = ws.build_job_map()?;
+    v.filter.retain_sweep(&mut job_map);
+    let jobs = v.filter.apply(select_jobs(&target, &job_map)?, ws);
+    if jobs.is_empty() {
+        return NoJobMatchesFiltersSnafu {
+            id: target.to_string(),
+        }
+        .fail();
+    }
+    if v.follow {
+        return follow(&mut io::stdout(), target.job, &jobs, ws);
+    }
+    let part = match (v.head, v.tail) {
+        (Some(lines), _) => LogPart::Head(lines),
+        (None, Some(lines)) => LogPart::Tail(lines),
+        (None, None) => LogPart::All,
+    };
+    let infos = if v.config || v.submission || v.traceback.is_some() || !v.format.is_text() {
+        HashMap::new()
+    } else {
+        get_job_infos(jobs.iter().map(|(id, _)| *id), false)?
+    };
+
+    let mut pager = if !v.no_pager && io::stdout().is_terminal() {
+        start_pager()
+    } else {
+        None
+    };
+    let mut out: Box<dyn Write> = match pager.as_mut().and_then(|pager| pager.stdin.take()) {
+        Some(stdin) => Box::new(stdin),
+        None => Box::new(io::stdout()),
+    };
+    let result = if v.config {
+        print_configs(&mut out, &jobs, false, ws)
+    } else if v.submission {
+        print_submissions(&mut out, &jobs, ws)
+    } else if let Some(scope) = v.traceback {
+        print_tracebacks(&mut out, &jobs, scope, ws)
+    } else if let Some(format) = v.format.records() {
+        write_log_records(&mut out, &jobs, format, part)
+    } else {
+        print_logs(&mut out, &jobs, &infos, part, ws)
+    };
+    // Closing its input lets the pager know that all output is there.
+    drop(out);
+    if let Some(mut pager) = pager {
+        let _ = pager.wait();
     }
+    ignore_broken_pipe(result)
 }
 
-fn search(s: SearchOpts) {
-    let pattern = s.pattern;
-    let regex = Regex::new(&pattern).unwrap();
-    let job_map = build_job_map("multirun").unwrap();
+enum SearchLine {
+    // Between groups of lines that are not adjacent, like grep's "--".
+    Separator,
+    Line {
+        number: usize,
+        text: String,
+        is_match: bool,
+        // Byte ranges of the matches, empty for context lines.
+        spans: Vec<(usize, usize)>,
+    },
+}
 
-    let mut entries: Vec<_> = job_map.iter().collect();
-    entries.sort_by(|a, b| b.0.cmp(a.0));
+// What searching a job reports, log by log and line by line, so that lines
+// can be printed while the log is still being read.
+enum SearchEvent {
+    // The following lines are from this log.
+    Log {
+        ending: &'static str,
+        log_fp: PathBuf,
+    },
+    // Matching lines and their context, sent in batches to keep the workers
+    // from waiting on the channel.
+    Lines(Vec<SearchLine>),
+    // The log was read to the end, with this many matching lines.
+    Count(usize),
+}
+
+// Events of a job that are not printed yet, per job that is searched ahead
+// of the one being printed. Workers wait once it is full, which keeps
+// memory bounded even for logs with millions of matches.
+const SEARCH_EVENT_BUFFER: usize = 16;
+
+const SEARCH_LINES_BATCH: usize = 256;
+
+fn format_search_line(s: &SearchOpts, line: &SearchLine) -> String {
+    let SearchLine::Line {
+        number,
+        text,
+        is_match,
+        spans,
+    } = line
+    else {
+        return "--".to_string();
+    };
+    let mut highlighted = String::new();
+    let mut last = 0;
+    for &(start, end) in spans {
+        highlighted.push_str(&text[last..start]);
+        highlighted.push_str(&text[start..end].red().to_string());
+        last = end;
+    }
+    highlighted.push_str(&text[last..]);
+    if !s.line_number {
+        return highlighted;
+    }
+    let separator = if *is_match { ":" } else { "-" };
+    format!("{}{separator} {highlighted}", number.to_string().green())
+}
+
+// Streams the log line by line, so that even multi-GB logs never have to be
+// held in memory at once. Matches are passed to emit with context lines
+// around them like grep does. Returns the number of matching lines, or None
+// if emit asked to stop.
+fn matching_lines(
+    log_fp: &Path,
+    regex: &Regex,
+    s: &SearchOpts,
+    emit: &mut dyn FnMut(SearchLine) -> bool,
+) -> Option<usize> {
+    let mut count = 0;
+    let Ok(file) = File::open(log_fp) else {
+        return Some(0);
+    };
+    let (before, after) = s.context_sizes();
+    let max_count = s.effective_max_count();
+
+    let mut reader = BufReader::new(file);
+    let mut buf = Vec::new();
+    let mut previous: VecDeque<(usize, String)> = VecDeque::with_capacity(before);
+    let mut after_left = 0;
+    let mut last_printed: Option<usize> = None;
+    let mut line_number = 0;
+    loop {
+        let done = max_count.is_some_and(|max| count >= max);
+        if done && after_left == 0 {
+            break;
+        }
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        line_number += 1;
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+
+        if !done && regex.is_match(line) {
+            count += 1;
+            after_left = after;
+            if !s.prints_lines() {
+                continue;
+            }
+            let first = previous.front().map_or(line_number, |(n, _)| *n);
+            if (before > 0 || after > 0)
+                && last_printed.is_some_and(|n| n + 1 < first)
+                && !emit(SearchLine::Separator)
+            {
+                return None;
+            }
+            for (number, text) in previous.drain(..) {
+                let context = SearchLine::Line {
+                    number,
+                    text,
+                    is_match: false,
+                    spans: Vec::new(),
+                };
+                if !emit(context) {
+                    return None;
+                }
+            }
+            let matching = SearchLine::Line {
+                number: line_number,
+                text: line.to_string(),
+                is_match: true,
+                spans: regex
+                    .find_iter(line)
+                    .map(|m| (m.start(), m.end()))
+                    .collect(),
+            };
+            if !emit(matching) {
+                return None;
+            }
+            last_printed = Some(line_number);
+        } else if after_left > 0 {
+            after_left -= 1;
+            let context = SearchLine::Line {
+                number: line_number,
+                text: line.to_string(),
+                is_match: false,
+                spans: Vec::new(),
+            };
+            if !emit(context) {
+                return None;
+            }
+            last_printed = Some(line_number);
+        } else if before > 0 {
+            if previous.len() == before {
+                previous.pop_front();
+            }
+            previous.push_back((line_number, line.to_string()));
+        }
+    }
+    Some(count)
+}
+
+// Sends the events of all logs of a job, until the receiver is gone.
+fn search_job(
+    job: &Job,
+    regex: &Regex,
+    s: &SearchOpts,
+    events: &mpsc::SyncSender<SearchEvent>,
+) -> Result<(), mpsc::SendError<SearchEvent>> {
+    for ending in s.stream.endings() {
+        let Ok(log_fp) = get_log_pathbuf(&job.dir, ending) else {
+            continue;
+        };
+        events.send(SearchEvent::Log {
+            ending,
+            log_fp: log_fp.clone(),
+        })?;
+        let mut batch = Vec::new();
+        let mut emit = |line| {
+            batch.push(line);
+            batch.len() < SEARCH_LINES_BATCH
+                || events
+                    .send(SearchEvent::Lines(mem::take(&mut batch)))
+                    .is_ok()
+        };
+        let Some(count) = matching_lines(&log_fp, regex, s, &mut emit) else {
+            return Ok(());
+        };
+        if !batch.is_empty() {
+            events.send(SearchEvent::Lines(batch))?;
+        }
+        events.send(SearchEvent::Count(count))?;
+    }
+    Ok(())
+}
+
+fn search(s: SearchOpts, ws: &Workspace) -> PResult<()> {
+    let regex = Regex::new(&s.pattern).context(InvalidRegexSnafu {
+        pattern: s.pattern.clone(),
+    })?;
+    // Has to happen before anything runs on the global pool.
+    if let Some(threads) = s.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .context(ThreadPoolSnafu)?;
+    }
+    let mut job_map = ws.build_job_map()?;
+    s.filter.retain_sweep(&mut job_map);
+
+    // Newest jobs first, array tasks of a job in descending order.
+    let mut entries: Vec<_> = job_map.iter().rev().collect();
 
     let active_jobs = if s.active {
-        get_active_slurm_jobs()
+        slurm::get_active_slurm_jobs()?
     } else {
         Vec::new()
     };
 
-    for (id, dir) in entries.iter() {
-        if s.active && !active_jobs.contains(id) {
-            continue;
+    entries.retain(|(id, _)| !s.active || active_jobs.contains(id));
+    entries = s.filter.apply(entries, ws);
+    if !s.state.is_empty() {
+        let infos = get_job_infos(entries.iter().map(|(id, _)| *id), true)?;
+        entries.retain(|(id, _)| matches_state(&s.state, infos.get(id)));
+    }
+
+    let mut writer = s.format.records().map(|format| {
+        RecordWriter::new(
+            format,
+            if s.ids {
+                &["job"]
+            } else if s.files_with_matches {
+                &["job", "path", "stream"]
+            } else if s.count {
+                &["job", "path", "stream", "count"]
+            } else {
+                &[
+                    "job",
+                    "path",
+                    "stream",
+                    "line_number",
+                    "line",
+                    "is_match",
+                    "matches",
+                ]
+            },
+        )
+    });
+    let mut out = io::stdout();
+    let mut matched = Vec::new();
+
+    // Logs are searched in parallel, but printed in the order of the sorted
+    // entries. Workers take jobs from a queue in that order, at most a few
+    // jobs ahead of the one being printed, and stream their events to it.
+    // Once the output is closed, the receivers are dropped and the workers
+    // stop at their next send.
+    let threads = rayon::current_num_threads();
+    let ahead = 2 * threads;
+    let (jobs_tx, jobs_rx) = mpsc::channel::<(usize, mpsc::SyncSender<SearchEvent>)>();
+    let jobs_rx = Mutex::new(jobs_rx);
+    let result = thread::scope(|scope| -> io::Result<()> {
+        // Moved in, so that the workers also stop when printing fails.
+        let jobs_tx = jobs_tx;
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let next = jobs_rx.lock().unwrap().recv();
+                let Ok((i, events)) = next else {
+                    break;
+                };
+                let _ = search_job(entries[i].1, &regex, &s, &events);
+            });
+        }
+
+        let mut pending = VecDeque::new();
+        let mut queued = 0;
+        for (id, job) in &entries {
+            while queued < entries.len() && pending.len() < ahead {
+                let (events_tx, events_rx) = mpsc::sync_channel(SEARCH_EVENT_BUFFER);
+                let _ = jobs_tx.send((queued, events_tx));
+                pending.push_back(events_rx);
+                queued += 1;
+            }
+            let events = pending.pop_front().unwrap();
+            if print_job_search(&mut out, writer.as_mut(), &s, ws, id, job, events)? {
+                matched.push(id.to_string());
+            }
+        }
+        if let Some(writer) = &mut writer {
+            writer.finish(&mut out)?;
+        }
+        Ok(())
+    });
+    ignore_broken_pipe(result)?;
+    if s.cancel {
+        cancel_jobs(&matched, s.yes)?;
+    }
+    Ok(())
+}
+
+// Asks on stderr, to keep stdout for the actual output. Anything but y or
+// yes, including no answer at all, is a no.
+fn confirm(question: &str) -> PResult<bool> {
+    eprint!("{question} [y/N] ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).context(TerminalSnafu)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn cancel_jobs(jobs: &[String], yes: bool) -> PResult<()> {
+    if jobs.is_empty() {
+        eprintln!("No jobs to cancel.");
+        return Ok(());
+    }
+    let list = jobs.join(" ");
+    if !yes && !confirm(&format!("Cancel {} job(s): {list}?", jobs.len()))? {
+        eprintln!("Nothing was cancelled.");
+        return Ok(());
+    }
+    if !slurm::cancel_jobs(jobs)? {
+        return CancelFailedSnafu { jobs: list }.fail();
+    }
+    eprintln!("Cancelled {list}.");
+    Ok(())
+}
+
+// Separators have no record, they only exist for readability.
+fn search_record(id: &Value, log_fp: &Path, ending: &str, line: SearchLine) -> Option<Vec<Value>> {
+    let SearchLine::Line {
+        number,
+        text,
+        is_match,
+        spans,
+    } = line
+    else {
+        return None;
+    };
+    let spans: Vec<Value> = spans
+        .into_iter()
+        .map(|(start, end)| Value::from(vec![start, end]))
+        .collect();
+    Some(vec![
+        id.clone(),
+        log_fp.to_string_lossy().into(),
+        ending.into(),
+        number.into(),
+        text.into(),
+        is_match.into(),
+        spans.into(),
+    ])
+}
+
+// Prints the search results of a job as its events come in. Returns whether
+// any of its logs matched.
+fn print_job_search(
+    out: &mut dyn Write,
+    mut writer: Option<&mut RecordWriter>,
+    s: &SearchOpts,
+    ws: &Workspace,
+    id: &JobId,
+    job: &Job,
+    events: mpsc::Receiver<SearchEvent>,
+) -> io::Result<bool> {
+    let record_id = Value::from(id.to_string());
+    let mut log = None;
+    let mut total = 0;
+    let mut header_printed = false;
+    for event in events {
+        match event {
+            SearchEvent::Log { ending, log_fp } => log = Some((ending, log_fp)),
+            SearchEvent::Lines(lines) => {
+                let Some((ending, log_fp)) = &log else {
+                    continue;
+                };
+                for line in lines {
+                    if let Some(writer) = writer.as_deref_mut() {
+                        if let Some(values) = search_record(&record_id, log_fp, ending, line) {
+                            writer.write(out, values)?;
+                        }
+                        continue;
+                    }
+                    if !header_printed {
+                        let header = ws.with_root(format!("{}:", job.dir.to_string_lossy()), job);
+                        let dashes = "-".repeat(header.len());
+                        writeln!(out, "{}\n{dashes}", header.bold())?;
+                        header_printed = true;
+                    }
+                    let line = format_search_line(s, &line);
+                    writeln!(out, "{} {line}", stream_tag(ending))?;
+                }
+            }
+            SearchEvent::Count(count) => {
+                total += count;
+                let Some((ending, log_fp)) = log.take().filter(|_| count > 0) else {
+                    continue;
+                };
+                let path = log_fp.to_string_lossy();
+                match writer.as_deref_mut() {
+                    _ if s.ids => {}
+                    Some(writer) if s.files_with_matches => {
+                        writer.write(out, vec![record_id.clone(), path.into(), ending.into()])?
+                    }
+                    Some(writer) if s.count => writer.write(
+                        out,
+                        vec![record_id.clone(), path.into(), ending.into(), count.into()],
+                    )?,
+                    None if s.files_with_matches => writeln!(out, "{path}")?,
+                    _ => {}
+                }
+            }
         }
-        let log_fp = get_log_pathbuf(dir, "out");
-        if log_fp.is_err() {
+    }
+    if total == 0 {
+        return Ok(false);
+    }
+    match writer {
+        Some(writer) if s.ids => writer.write(out, vec![record_id])?,
+        None if s.ids => writeln!(out, "{id}")?,
+        None if s.count => writeln!(
+            out,
+            "{}",
+            ws.with_root(format!("{}: {total}", job.dir.to_string_lossy()), job)
+        )?,
+        _ => {}
+    }
+    Ok(true)
+}
+
+struct ListRow<'a> {
+    id: &'a JobId,
+    job: &'a Job,
+    out: Option<fs::Metadata>,
+    err: Option<fs::Metadata>,
+}
+
+impl ListRow<'_> {
+    fn size(&self) -> u64 {
+        [&self.out, &self.err]
+            .iter()
+            .filter_map(|m| m.as_ref())
+            .map(|m| m.len())
+            .sum()
+    }
+
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        [&self.out, &self.err]
+            .iter()
+            .filter_map(|m| m.as_ref()?.modified().ok())
+            .max()
+    }
+}
+
+fn write_list_records(
+    out: &mut dyn Write,
+    rows: &[ListRow],
+    results: &[Option<submitit::ResultStatus>],
+    infos: &HashMap<JobId, JobInfo>,
+    format: RecordFormat,
+) -> io::Result<()> {
+    let mut writer = RecordWriter::new(
+        format,
+        &[
+            "job",
+            "task",
+            "sweep",
+            "out_bytes",
+            "err_bytes",
+            "modified",
+            "result",
+            "state",
+            "exit_code",
+            "elapsed",
+            "max_rss_bytes",
+            "root",
+            "dir",
+        ],
+    );
+    for (row, result) in rows.iter().zip(results) {
+        let info = infos.get(row.id);
+        writer.write(
+            out,
+            vec![
+                row.id.job.into(),
+                row.id.array_index.into(),
+                row.job.sweep_name().into(),
+                row.out.as_ref().map(|m| m.len()).into(),
+                row.err.as_ref().map(|m| m.len()).into(),
+                row.modified()
+                    .map(|t| DateTime::<Local>::from(t).to_rfc3339())
+                    .into(),
+                result.map(|result| result.to_string()).into(),
+                info.map(|info| info.state.to_string()).into(),
+                info.and_then(|info| info.exit_code.clone()).into(),
+                info.and_then(|info| info.elapsed.clone()).into(),
+                info.and_then(|info| info.max_rss).into(),
+                row.job.root.to_string_lossy().into(),
+                row.job.dir.to_string_lossy().into(),
+            ],
+        )?;
+    }
+    writer.finish(out)
+}
+
+fn list(l: ListOpts, ws: &Workspace) -> PResult<()> {
+    let mut job_map = ws.build_job_map()?;
+    l.filter.retain_sweep(&mut job_map);
+
+    let jobs: Vec<(&JobId, &Job)> = job_map
+        .iter()
+        .rev()
+        .filter(|(_, job)| {
+            let sweep_time = job.sweep_time();
+            l.since
+                .is_none_or(|since| sweep_time.is_some_and(|t| t >= since))
+                && l.until
+                    .is_none_or(|until| sweep_time.is_some_and(|t| t <= until))
+        })
+        .collect();
+    let mut rows: Vec<ListRow> = l
+        .filter
+        .apply(jobs, ws)
+        .into_iter()
+        .map(|(id, job)| ListRow {
+            id,
+            job,
+            out: job.log_metadata("out"),
+            err: job.log_metadata("err"),
+        })
+        .collect();
+    // Without a state filter, only the jobs that end up in the table have to
+    // be looked up.
+    let mut infos = None;
+    if !l.state.is_empty() {
+        let state_infos = get_job_infos(rows.iter().map(|row| row.id), true)?;
+        rows.retain(|row| matches_state(&l.state, state_infos.get(row.id)));
+        infos = Some(state_infos);
+    }
+    match l.sort {
+        ListSort::Id => {}
+        ListSort::Time => rows.sort_by_key(|row| std::cmp::Reverse(row.modified())),
+        ListSort::Size => rows.sort_by_key(|row| std::cmp::Reverse(row.size())),
+    }
+    rows.truncate(l.limit.unwrap_or(rows.len()));
+    let infos = match infos {
+        Some(infos) => infos,
+        None => get_job_infos(rows.iter().map(|row| row.id), false)?,
+    };
+
+    let results: Vec<Option<submitit::ResultStatus>> = rows
+        .par_iter()
+        .map(|row| submitit::result_status(&row.job.dir))
+        .collect();
+    if let Some(format) = l.format.records() {
+        return ignore_broken_pipe(write_list_records(
+            &mut io::stdout(),
+            &rows,
+            &results,
+            &infos,
+            format,
+        ));
+    }
+
+    let format_size = |m: &Option<fs::Metadata>| {
+        m.as_ref()
+            .map_or("-".to_string(), |m| table::format_size(m.len()))
+    };
+    let table_rows: Vec<Vec<String>> = rows
+        .iter()
+        .zip(&results)
+        .map(|(row, result)| {
+            let mut cells = vec![
+                row.id.job.to_string(),
+                row.id
+                    .array_index
+                    .map_or("-".to_string(), |index| index.to_string()),
+                row.job.sweep_time().map_or("-".to_string(), |t| {
+                    t.format("%Y-%m-%d %H:%M:%S").to_string()
+                }),
+                format_size(&row.out),
+                format_size(&row.err),
+                row.modified().map_or("-".to_string(), |t| {
+                    DateTime::<Local>::from(t)
+                        .format("%Y-%m-%d %H:%M:%S")
+                        .to_string()
+                }),
+                result.map_or("-".to_string(), |result| result.to_string()),
+            ];
+            let info = infos.get(row.id);
+            cells.push(info.map_or("-".to_string(), |info| info.state.to_string()));
+            for field in [
+                info.and_then(|info| info.exit_code.clone()),
+                info.and_then(|info| info.elapsed.clone()),
+                info.and_then(|info| info.max_rss.map(table::format_size)),
+            ] {
+                cells.push(field.unwrap_or("-".to_string()));
+            }
+            if ws.roots.len() > 1 {
+                cells.push(row.job.root.display().to_string());
+            }
+            cells
+        })
+        .collect();
+    let mut headers = vec![
+        "JOB", "TASK", "SWEEP", "OUT", "ERR", "MODIFIED", "RESULT", "STATE", "EXIT", "ELAPSED",
+        "MAXRSS",
+    ];
+    if ws.roots.len() > 1 {
+        headers.push("ROOT");
+    }
+    ignore_broken_pipe(table::print_table(&mut io::stdout(), &headers, &table_rows))
+}
+
+// Enough to catch the final submitit messages and a traceback.
+const ERR_TAIL_BYTES: u64 = 16 * 1024;
+
+fn job_outcome(job: &Job, info: Option<&JobInfo>) -> Outcome {
+    // The log only has to be read if Slurm cannot tell.
+    let outcome = sweep::outcome(info, None);
+    if outcome != Outcome::Unknown {
+        return outcome;
+    }
+    let err_tail = get_log_pathbuf(&job.dir, "err")
+        .ok()
+        .and_then(|log_fp| get_log_tail(log_fp, ERR_TAIL_BYTES));
+    sweep::outcome(info, err_tail.as_deref())
+}
+
+fn sweeps(o: SweepsOpts, ws: &Workspace) -> PResult<()> {
+    let job_map = ws.build_job_map()?;
+    let infos = get_job_infos(job_map.keys(), false)?;
+
+    // Keyed by name first, so sweeps come out in chronological order.
+    let mut sweeps: BTreeMap<_, Vec<(&JobId, &Job)>> = BTreeMap::new();
+    for (id, job) in &job_map {
+        let Some(sweep_dir) = job.sweep_dir() else {
             continue;
+        };
+        sweeps
+            .entry((job.sweep_name(), sweep_dir.to_path_buf()))
+            .or_default()
+            .push((id, job));
+    }
+
+    let rows: Vec<Vec<String>> = sweeps
+        .iter()
+        .rev()
+        .take(o.limit.unwrap_or(usize::MAX))
+        .map(|((name, sweep_dir), jobs)| {
+            let outcomes: Vec<Outcome> = jobs
+                .par_iter()
+                .map(|(id, job)| job_outcome(job, infos.get(id)))
+                .collect();
+            let count = |outcome| outcomes.iter().filter(|o| **o == outcome).count();
+            let mut cells = vec![
+                name.clone().unwrap_or("-".to_string()),
+                jobs.len().to_string(),
+                count(Outcome::Done).to_string(),
+                count(Outcome::Failed).to_string(),
+                count(Outcome::Running).to_string(),
+                count(Outcome::Pending).to_string(),
+                sweep::varying_keys(sweep_dir).join(","),
+            ];
+            if ws.roots.len() > 1 {
+                cells.push(jobs[0].1.root.display().to_string());
+            }
+            cells
+        })
+        .collect();
+    let mut headers = vec![
+        "SWEEP", "JOBS", "DONE", "FAILED", "RUNNING", "PENDING", "VARYING",
+    ];
+    if ws.roots.len() > 1 {
+        headers.push("ROOT");
+    }
+    ignore_broken_pipe(table::print_table(&mut io::stdout(), &headers, &rows))
+}
+
+// Failures are usually reported at the very end of the log, but a traceback
+// can be followed by plenty of shutdown noise.
+const FAILURE_TAIL_BYTES: u64 = 256 * 1024;
+// Keeps the table readable for exceptions with huge messages.
+const MAX_KEY_LINE_CHARS: usize = 160;
+
+fn failures(f: FailuresOpts, ws: &Workspace) -> PResult<()> {
+    let mut job_map = ws.build_job_map()?;
+    f.filter.retain_sweep(&mut job_map);
+    let entries = f.filter.apply(job_map.iter().rev().collect(), ws);
+
+    let infos = get_job_infos(entries.iter().map(|(id, _)| *id), false)?;
+    let mut found: Vec<(failures::Failure, &JobId)> = entries
+        .par_iter()
+        .filter_map(|(id, job)| {
+            // Running jobs and errors that were caught and logged do not
+            // count. Jobs that neither Slurm nor submitit can tell about may
+            // still have died, e.g. from a segfault.
+            match job_outcome(job, infos.get(id)) {
+                Outcome::Failed | Outcome::Unknown => {}
+                Outcome::Done | Outcome::Running | Outcome::Pending => return None,
+            }
+            let err_log_fp = get_log_pathbuf(&job.dir, "err").ok()?;
+            let err_tail = get_log_tail(err_log_fp, FAILURE_TAIL_BYTES)?;
+            Some((failures::classify(&err_tail)?, *id))
+        })
+        .collect();
+    // Stable, so jobs stay in descending order within each class.
+    found.sort_by_key(|(failure, _)| failure.class);
+
+    let mut rows = Vec::new();
+    let mut previous_class = None;
+    for (failure, id) in &found {
+        let class = (previous_class != Some(failure.class)).then(|| {
+            let count = found
+                .iter()
+                .filter(|(f, _)| f.class == failure.class)
+                .count();
+            format!("{} ({count})", failure.class)
+        });
+        previous_class = Some(failure.class);
+        let key_line: String = failure.key_line.chars().take(MAX_KEY_LINE_CHARS).collect();
+        rows.push(vec![class.unwrap_or_default(), id.to_string(), key_line]);
+    }
+    ignore_broken_pipe(table::print_table(
+        &mut io::stdout(),
+        &["FAILURE", "JOB", "KEY LINE"],
+        &rows,
+    ))
+}
+
+fn format_usage(usage: Option<efficiency::Use>) -> String {
+    usage
+        .and_then(efficiency::Use::ratio)
+        .map_or("-".to_string(), |ratio| format!("{:.0}%", ratio * 100.0))
+}
+
+fn format_overrequested(overrequested: &[(&str, f64)]) -> String {
+    overrequested
+        .iter()
+        .map(|(resource, factor)| format!("{resource} {factor:.1}x"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn efficiency(e: EfficiencyOpts, ws: &Workspace) -> PResult<()> {
+    let mut job_map = ws.build_job_map()?;
+    e.filter.retain_sweep(&mut job_map);
+    let jobs = match &e.jobid {
+        Some(target) => select_jobs(target, &job_map)?,
+        None => job_map.iter().collect(),
+    };
+    let jobs = e.filter.apply(jobs, ws);
+    let usages = slurm::get_job_usage(jobs.iter().map(|(id, _)| *id))?;
+    let requests: Vec<Option<submitit::SbatchRequest>> = jobs
+        .par_iter()
+        .map(|(_, job)| submitit::read_sbatch_request(&job.dir).ok())
+        .collect();
+
+    let mut rows = Vec::new();
+    // Keyed by name, so sweeps come out in chronological order. Also counts
+    // the overrequesting jobs of each sweep.
+    let mut sweeps: BTreeMap<String, (Vec<efficiency::Efficiency>, usize)> = BTreeMap::new();
+    for ((id, job), request) in jobs.iter().zip(&requests) {
+        let usage = usages.get(id);
+        let job_efficiency = efficiency::Efficiency::new(request.as_ref(), usage);
+        let overrequested = job_efficiency.overrequested(e.flag_factor);
+        let requested_mem = request
+            .as_ref()
+            .and_then(|request| request.mem_bytes(usage?.cpus, usage?.gpus));
+        let gpus = job_efficiency
+            .allocated_gpus
+            .or(job_efficiency.requested_gpus);
+        if !e.flagged || !overrequested.is_empty() {
+            rows.push(vec![
+                id.to_string(),
+                usage.map_or("-".to_string(), |usage| usage.state.to_string()),
+                usage
+                    .and_then(|usage| usage.elapsed)
+                    .map_or("-".to_string(), slurm::format_duration),
+                request
+                    .as_ref()
+                    .and_then(|request| request.time_limit)
+                    .map_or("-".to_string(), slurm::format_duration),
+                format_usage(job_efficiency.time),
+                usage
+                    .and_then(|usage| usage.max_rss)
+                    .map_or("-".to_string(), table::format_size),
+                requested_mem.map_or("-".to_string(), table::format_size),
+                format_usage(job_efficiency.mem),
+                format_usage(job_efficiency.cpu),
+                gpus.map_or("-".to_string(), |gpus| gpus.to_string()),
+                format_overrequested(&overrequested),
+            ]);
         }
-        let log_content = get_log_content(log_fp.unwrap()).unwrap_or("".to_string());
-        let matching_lines = log_content
-            .lines()
-            .filter_map(|line| {
-                regex.is_match(line).then(|| {
-                    regex.replace_all(line, |cap: &regex::Captures| cap[0].red().to_string())
-                })
+        let sweep = sweeps
+            .entry(job.sweep_name().unwrap_or("-".to_string()))
+            .or_default();
+        sweep.0.push(job_efficiency);
+        sweep.1 += usize::from(!overrequested.is_empty());
+    }
+    let sweep_rows: Vec<Vec<String>> = sweeps
+        .iter()
+        .map(|(name, (efficiencies, flagged))| {
+            let total = efficiency::Efficiency::total(efficiencies);
+            let completed = efficiencies.iter().filter(|job| job.completed).count();
+            vec![
+                name.clone(),
+                efficiencies.len().to_string(),
+                completed.to_string(),
+                format_usage(total.time),
+                format_usage(total.mem),
+                format_usage(total.cpu),
+                total.gpu_seconds.map_or("-".to_string(), |gpu_seconds| {
+                    format!("{:.1}", gpu_seconds as f64 / 3600.0)
+                }),
+                flagged.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut out = io::stdout();
+    let result = table::print_table(
+        &mut out,
+        &[
+            "JOB",
+            "STATE",
+            "ELAPSED",
+            "LIMIT",
+            "TIME USED",
+            "MAXRSS",
+            "MEM",
+            "MEM USED",
+            "CPU USED",
+            "GPUS",
+            "OVERREQUESTED",
+        ],
+        &rows,
+    )
+    .and_then(|()| writeln!(out))
+    .and_then(|()| {
+        table::print_table(
+            &mut out,
+            &[
+                "SWEEP",
+                "JOBS",
+                "COMPLETED",
+                "TIME USED",
+                "MEM USED",
+                "CPU USED",
+                "GPU HOURS",
+                "OVERREQUESTED",
+            ],
+            &sweep_rows,
+        )
+    });
+    ignore_broken_pipe(result)
+}
+
+fn print_reruns(
+    out: &mut dyn Write,
+    reruns: &[(String, resubmit::Rerun)],
+    r: &ResubmitOpts,
+) -> io::Result<()> {
+    for (jobs, rerun) in reruns {
+        writeln!(out, "# {jobs}\n{}", rerun.shell_command(&r.python))?;
+    }
+    if !r.execute {
+        writeln!(out, "# Dry run, pass --execute to launch these.")?;
+    }
+    Ok(())
+}
+
+fn resubmit(r: ResubmitOpts, ws: &Workspace) -> PResult<()> {
+    let mut job_map = ws.build_job_map()?;
+    r.filter.retain_sweep(&mut job_map);
+    let jobs = match &r.jobid {
+        Some(target) => select_jobs(target, &job_map)?,
+        None => job_map.iter().collect(),
+    };
+    let jobs = r.filter.apply(jobs, ws);
+    let infos = if r.failed {
+        get_job_infos(jobs.iter().map(|(id, _)| *id), false)?
+    } else {
+        HashMap::new()
+    };
+    let jobs: Vec<(&JobId, &Job)> = jobs
+        .into_par_iter()
+        .filter(|(id, job)| !r.failed || job_outcome(job, infos.get(id)) == Outcome::Failed)
+        .collect();
+    if jobs.is_empty() {
+        return ignore_broken_pipe(writeln!(io::stdout(), "No jobs to resubmit."));
+    }
+
+    // The jobs of a sweep share the script and Hydra's own overrides, e.g.
+    // the launcher.
+    let mut sweeps: BTreeMap<&Path, Vec<(&JobId, &Job)>> = BTreeMap::new();
+    for (id, job) in jobs {
+        sweeps
+            .entry(job.sweep_dir().unwrap_or(&job.dir))
+            .or_default()
+            .push((id, job));
+    }
+    let mut reruns = Vec::new();
+    for (sweep_dir, jobs) in &sweeps {
+        let (id, job) = jobs[0];
+        let hydra_job_dir = ws.hydra_job_dir(id, job)?;
+        let hydra_yaml = hydra::read_hydra_file(&hydra_job_dir, "hydra.yaml")?;
+        let script = match (&r.script, hydra::hydra_field(&hydra_yaml, "job", "name")) {
+            (Some(script), _) => script.clone(),
+            (None, Some(name)) => format!("{name}.py"),
+            (None, None) => {
+                return UnknownScriptSnafu {
+                    sweep_dir: *sweep_dir,
+                }
+                .fail()
+            }
+        };
+        let cwd = hydra::hydra_field(&hydra_yaml, "runtime", "cwd").map(PathBuf::from);
+        let sweep_dir = hydra::hydra_field(&hydra_yaml, "sweep", "dir")
+            .unwrap_or(resubmit::DEFAULT_SWEEP_DIR.to_string());
+        // -m already sets the mode.
+        let hydra_overrides: Vec<String> = hydra::overrides_list(&hydra_yaml, "hydra")
+            .into_iter()
+            .filter(|o| !o.starts_with("hydra.mode="))
+            .collect();
+        let overrides: Vec<Vec<String>> = jobs
+            .iter()
+            .map(|(id, job)| ws.job_overrides(id, job))
+            .collect::<PResult<_>>()?;
+        let sweep_name = jobs[0].1.sweep_name().unwrap_or("-".to_string());
+        let ids: Vec<String> = jobs.iter().map(|(id, _)| id.to_string()).collect();
+        let merged = (!r.per_job)
+            .then(|| resubmit::merge_overrides(&overrides))
+            .flatten();
+        let launches = match merged {
+            Some(merged) => vec![(ids.join(", "), merged)],
+            None => ids.into_iter().zip(overrides).collect(),
+        };
+        for (ids, overrides) in launches {
+            let rerun = resubmit::Rerun {
+                cwd: cwd.clone(),
+                script: script.clone(),
+                overrides: hydra_overrides.iter().cloned().chain(overrides).collect(),
+                sweep_dir: sweep_dir.clone(),
+            };
+            reruns.push((format!("{sweep_name}: {ids}"), rerun));
+        }
+    }
+
+    if reruns.len() > 1 {
+        for (n, (_, rerun)) in reruns.iter_mut().enumerate() {
+            rerun.number_sweep_dir(n);
+        }
+    }
+    ignore_broken_pipe(print_reruns(&mut io::stdout(), &reruns, &r))?;
+    if !r.execute {
+        return Ok(());
+    }
+
+    let mut children = Vec::new();
+    for (_, rerun) in &reruns {
+        let command = rerun.shell_command(&r.python);
+        let mut process = ProcCommand::new(&r.python);
+        process.args(rerun.args());
+        if let Some(cwd) = &rerun.cwd {
+            process.current_dir(cwd);
+        }
+        let child = process.spawn().context(ResubmitSnafu {
+            command: command.clone(),
+        })?;
+        children.push((command, child));
+    }
+    // The submitit launcher only exits once its jobs are done, so all sweeps
+    // are launched before waiting for any of them.
+    for (command, mut child) in children {
+        let status = child.wait().context(ResubmitSnafu {
+            command: command.clone(),
+        })?;
+        if !status.success() {
+            return ResubmitFailedSnafu { command, status }.fail();
+        }
+    }
+    Ok(())
+}
+
+struct JobMetrics<'a> {
+    id: &'a JobId,
+    overrides: Vec<(String, String)>,
+    records: Vec<metrics::Record>,
+}
+
+fn format_metric(value: Option<f64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn format_stat(records: &[metrics::Record], metric: usize, stat: Stat, maximize: bool) -> String {
+    if stat == Stat::Trend {
+        return plot::sparkline(&metrics::values(records, metric), plot::SPARK_WIDTH);
+    }
+    format_metric(metrics::summarize(records, metric, stat, maximize))
+}
+
+fn metrics(m: MetricsOpts, ws: &Workspace) -> PResult<()> {
+    let regex = Regex::new(&m.pattern).context(InvalidRegexSnafu {
+        pattern: m.pattern.clone(),
+    })?;
+    let names = metrics::metric_names(&regex);
+    if names.is_empty() {
+        return NoMetricGroupsSnafu { pattern: m.pattern }.fail();
+    }
+    if let Some(name) = m
+        .maximize
+        .iter()
+        .chain(&m.plot)
+        .find(|name| !names.contains(name))
+    {
+        return UnknownMetricSnafu { name: name.clone() }.fail();
+    }
+    let mut job_map = ws.build_job_map()?;
+    m.filter.retain_sweep(&mut job_map);
+
+    let entries: Vec<_> = job_map.iter().collect();
+    let jobs: Vec<JobMetrics> = entries
+        .into_par_iter()
+        .filter_map(|(id, job)| {
+            let overrides = ws.job_overrides(id, job).unwrap_or_default();
+            if !m.filter.matches(&overrides) {
+                return None;
+            }
+            let log_fp = get_log_pathbuf(&job.dir, "out").ok()?;
+            let records = metrics::extract_records(&log_fp, &regex, &names)?;
+            let overrides = overrides
+                .iter()
+                .filter_map(|o| hydra::split_override(o))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            (!records.is_empty()).then_some(JobMetrics {
+                id,
+                overrides,
+                records,
             })
-            .collect::<Vec<_>>();
+        })
+        .collect();
 
-        if matching_lines.len() == 0 {
-            continue;
+    // Overrides become columns. In the table only the ones that differ
+    // between jobs are shown, to keep it narrow.
+    let mut keys: Vec<String> = Vec::new();
+    for job in &jobs {
+        for (key, _) in &job.overrides {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
         }
+    }
+    let override_value = |job: &JobMetrics, key: &str| {
+        job.overrides
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    };
+    if m.format.is_text() {
+        keys.retain(|key| {
+            let first = override_value(&jobs[0], key);
+            jobs.iter().any(|job| override_value(job, key) != first)
+        });
+    }
+    let maximize: Vec<bool> = names.iter().map(|name| m.maximize.contains(name)).collect();
+
+    if let Some(name) = &m.plot {
+        let metric = names.iter().position(|n| n == name).unwrap();
+        let series: Vec<Vec<f64>> = jobs
+            .iter()
+            .map(|job| metrics::values(&job.records, metric))
+            .collect();
+        let legends: Vec<String> = jobs
+            .iter()
+            .enumerate()
+            .map(|(s, job)| {
+                let mut legend = vec![plot::marker(s), job.id.to_string()];
+                legend.extend(
+                    keys.iter()
+                        .filter_map(|key| Some(format!("{key}={}", override_value(job, key)?))),
+                );
+                legend.join(" ")
+            })
+            .collect();
+        return ignore_broken_pipe(print_plot(&mut io::stdout(), name, &series, &legends));
+    }
+
+    if matches!(m.format, OutputFormat::Json | OutputFormat::Jsonl) {
+        let objects: Vec<serde_json::Value> = jobs
+            .iter()
+            .map(|job| {
+                let overrides: serde_json::Map<_, _> = job
+                    .overrides
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone().into()))
+                    .collect();
+                let mut object = serde_json::json!({
+                    "job": job.id.to_string(),
+                    "overrides": overrides,
+                });
+                if m.series {
+                    let records: Vec<serde_json::Map<_, _>> = job
+                        .records
+                        .iter()
+                        .map(|record| {
+                            names
+                                .iter()
+                                .zip(record)
+                                .filter_map(|(name, value)| Some((name.clone(), (*value)?.into())))
+                                .collect()
+                        })
+                        .collect();
+                    object["records"] = records.into();
+                } else {
+                    let summary: serde_json::Map<_, _> = names
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| {
+                            let stats: serde_json::Map<_, _> = m
+                                .stats
+                                .iter()
+                                .map(|stat| {
+                                    let value = if *stat == Stat::Trend {
+                                        metrics::values(&job.records, i).into()
+                                    } else {
+                                        metrics::summarize(&job.records, i, *stat, maximize[i])
+                                            .into()
+                                    };
+                                    (stat.to_string(), value)
+                                })
+                                .collect();
+                            (name.clone(), stats.into())
+                        })
+                        .collect();
+                    object["metrics"] = summary.into();
+                }
+                object
+            })
+            .collect();
+        return ignore_broken_pipe(print_metric_objects(&mut io::stdout(), m.format, &objects));
+    }
 
-        if s.ids {
-            println!("{id}");
+    let mut headers = vec!["job".to_string()];
+    headers.extend(keys.iter().cloned());
+    if m.series {
+        headers.push("step".to_string());
+        headers.extend(names.iter().cloned());
+    } else {
+        for name in &names {
+            headers.extend(m.stats.iter().map(|stat| format!("{name}:{stat}")));
+        }
+    }
+    let mut rows = Vec::new();
+    for job in &jobs {
+        let mut row = vec![job.id.to_string()];
+        row.extend(
+            keys.iter()
+                .map(|key| override_value(job, key).unwrap_or_default()),
+        );
+        if m.series {
+            for (step, record) in job.records.iter().enumerate() {
+                let mut row = row.clone();
+                row.push(step.to_string());
+                row.extend(record.iter().map(|value| format_metric(*value)));
+                rows.push(row);
+            }
         } else {
-            let header = format!("{}:", dir.to_string_lossy());
-            let dashes = "-".repeat(header.len());
-            println!("{}\n{dashes}", header.bold());
-            for line in matching_lines {
-                println!("{}", line);
+            for (i, maximize) in maximize.iter().enumerate() {
+                row.extend(
+                    m.stats
+                        .iter()
+                        .map(|stat| format_stat(&job.records, i, *stat, *maximize)),
+                );
             }
+            rows.push(row);
+        }
+    }
+
+    ignore_broken_pipe(print_metrics(&mut io::stdout(), m.format, &headers, &rows))
+}
+
+fn print_plot(
+    out: &mut dyn Write,
+    name: &str,
+    series: &[Vec<f64>],
+    legends: &[String],
+) -> io::Result<()> {
+    writeln!(out, "{}", name.bold())?;
+    for line in plot::line_plot(series, plot::PLOT_WIDTH, plot::PLOT_HEIGHT) {
+        writeln!(out, "{line}")?;
+    }
+    for legend in legends {
+        writeln!(out, "{legend}")?;
+    }
+    Ok(())
+}
+
+fn print_metric_objects(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    objects: &[serde_json::Value],
+) -> io::Result<()> {
+    if format == OutputFormat::Jsonl {
+        for object in objects {
+            writeln!(out, "{object}")?;
         }
+        return Ok(());
     }
+    serde_json::to_writer_pretty(&mut *out, objects)?;
+    writeln!(out)
 }
 
-fn main() {
-    let cli = Cli::parse();
-    match cli.command {
-        Command::View(opts) => view(opts),
-        Command::Search(opts) => search(opts),
+fn print_metrics(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    headers: &[String],
+    rows: &[Vec<String>],
+) -> io::Result<()> {
+    if format == OutputFormat::Csv {
+        writeln!(out, "{}", table::csv_row(headers))?;
+        for row in rows {
+            writeln!(out, "{}", table::csv_row(row))?;
+        }
+        return Ok(());
+    }
+    let headers: Vec<String> = headers.iter().map(|h| h.to_uppercase()).collect();
+    let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+    table::print_table(out, &headers, rows)
+}
+
+fn reindex(ws: &Workspace) -> PResult<()> {
+    let mut out = io::stdout();
+    for root in &ws.roots {
+        let n_jobs = index::reindex(root)?;
+        ignore_broken_pipe(writeln!(
+            out,
+            "Indexed {n_jobs} jobs in {}.",
+            root.display()
+        ))?;
+    }
+    Ok(())
+}
+
+fn cancel(c: CancelOpts) -> PResult<()> {
+    let jobs: Vec<String> = c.jobids.iter().map(|id| id.to_string()).collect();
+    cancel_jobs(&jobs, c.yes)
+}
+
+fn run(cli: Cli) -> PResult<()> {
+    // Cancelling only talks to Slurm, so it works without any multirun root.
+    let command = match cli.command {
+        Command::Cancel(opts) => return cancel(opts),
+        command => command,
+    };
+    let ws = Workspace {
+        roots: resolve_roots(cli.root)?,
+        use_cache: !cli.no_cache,
+        job_dirs: hydra::JobDirs::default(),
+    };
+    match command {
+        Command::View(opts) => view(opts, &ws),
+        Command::Search(opts) => search(opts, &ws),
+        Command::Reindex => reindex(&ws),
+        Command::List(opts) => list(opts, &ws),
+        Command::Config(opts) => config(opts, &ws),
+        Command::Sweeps(opts) => sweeps(opts, &ws),
+        Command::Failures(opts) => failures(opts, &ws),
+        Command::Metrics(opts) => metrics(opts, &ws),
+        Command::Efficiency(opts) => efficiency(opts, &ws),
+        Command::Resubmit(opts) => resubmit(opts, &ws),
+        Command::Cancel(opts) => cancel(opts),
+        Command::Tui => tui::tui(&ws),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{} {e}", "error:".red().bold());
+            ExitCode::FAILURE
+        }
     }
 }
@@ -0,0 +1,85 @@
+use clap::ValueEnum;
+use regex::Regex;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// The values extracted from one matching log line, one per metric. A metric
+// is None if its group did not take part in the match or is not a number.
+pub type Record = Vec<Option<f64>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Stat {
+    /// The value logged last
+    Last,
+    /// The minimum, or the maximum for metrics given to --maximize
+    Best,
+    Min,
+    Max,
+    /// A sparkline of all values
+    Trend,
+}
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        write!(f, "{name}")
+    }
+}
+
+// Every named capture group of the pattern is a metric, e.g. loss and acc in
+//   epoch (?<epoch>\d+) \| loss (?<loss>[\d.]+) \| acc (?<acc>[\d.]+)
+pub fn metric_names(pattern: &Regex) -> Vec<String> {
+    pattern
+        .capture_names()
+        .flatten()
+        .map(|name| name.to_string())
+        .collect()
+}
+
+// Streams the log and extracts a record from every line matching the pattern.
+// Lines without a single numeric value are skipped.
+pub fn extract_records(log_fp: &Path, pattern: &Regex, names: &[String]) -> Option<Vec<Record>> {
+    let mut reader = BufReader::new(File::open(log_fp).ok()?);
+    let mut buf = Vec::new();
+    let mut records = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\n', '\r']);
+        let Some(caps) = pattern.captures(line) else {
+            continue;
+        };
+        let record: Record = names
+            .iter()
+            .map(|name| caps.name(name)?.as_str().trim().parse().ok())
+            .collect();
+        if record.iter().any(Option::is_some) {
+            records.push(record);
+        }
+    }
+    Some(records)
+}
+
+pub fn values(records: &[Record], metric: usize) -> Vec<f64> {
+    records.iter().filter_map(|record| record[metric]).collect()
+}
+
+// The trend is not a single number and yields None.
+pub fn summarize(records: &[Record], metric: usize, stat: Stat, maximize: bool) -> Option<f64> {
+    let mut values = records.iter().filter_map(|record| record[metric]);
+    match (stat, maximize) {
+        (Stat::Trend, _) => None,
+        (Stat::Last, _) => values.next_back(),
+        (Stat::Min, _) | (Stat::Best, false) => values.reduce(f64::min),
+        (Stat::Max, _) | (Stat::Best, true) => values.reduce(f64::max),
+    }
+}
@@ -0,0 +1,116 @@
+use crate::table::csv_row;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable, with colors on a terminal
+    #[value(alias = "table")]
+    Text,
+    Csv,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub fn is_text(self) -> bool {
+        self == OutputFormat::Text
+    }
+
+    // The machine readable format to write records in, None for text.
+    pub fn records(self) -> Option<RecordFormat> {
+        match self {
+            OutputFormat::Text => None,
+            OutputFormat::Csv => Some(RecordFormat::Csv),
+            OutputFormat::Json => Some(RecordFormat::Json),
+            OutputFormat::Jsonl => Some(RecordFormat::Jsonl),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Csv,
+    Json,
+    Jsonl,
+}
+
+// Nested arrays like the match spans [[0, 5], [9, 12]] become "0-5 9-12".
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Array(pair) => pair.iter().map(csv_cell).collect::<Vec<_>>().join("-"),
+                item => csv_cell(item),
+            })
+            .collect::<Vec<_>>()
+            .join(" "),
+        value => value.to_string(),
+    }
+}
+
+// Writes records with a fixed set of fields as they come in, so that large
+// results are streamed instead of collected first. finish has to be called
+// after the last record to close the JSON array.
+pub struct RecordWriter {
+    format: RecordFormat,
+    keys: Vec<&'static str>,
+    records: usize,
+}
+
+impl RecordWriter {
+    pub fn new(format: RecordFormat, keys: &[&'static str]) -> Self {
+        RecordWriter {
+            format,
+            keys: keys.to_vec(),
+            records: 0,
+        }
+    }
+
+    pub fn write(&mut self, out: &mut dyn Write, values: Vec<Value>) -> io::Result<()> {
+        let first = self.records == 0;
+        self.records += 1;
+        match self.format {
+            RecordFormat::Csv => {
+                if first {
+                    let keys: Vec<String> = self.keys.iter().map(|key| key.to_string()).collect();
+                    writeln!(out, "{}", csv_row(&keys))?;
+                }
+                let cells: Vec<String> = values.iter().map(csv_cell).collect();
+                writeln!(out, "{}", csv_row(&cells))
+            }
+            RecordFormat::Json | RecordFormat::Jsonl => {
+                let object: Map<String, Value> = self
+                    .keys
+                    .iter()
+                    .map(|key| key.to_string())
+                    .zip(values)
+                    .collect();
+                let object = Value::Object(object);
+                match (self.format, first) {
+                    (RecordFormat::Json, true) => write!(out, "[\n{object}"),
+                    (RecordFormat::Json, false) => write!(out, ",\n{object}"),
+                    _ => writeln!(out, "{object}"),
+                }
+            }
+        }
+    }
+
+    pub fn finish(&mut self, out: &mut dyn Write) -> io::Result<()> {
+        match (self.format, self.records) {
+            (RecordFormat::Json, 0) => writeln!(out, "[]"),
+            (RecordFormat::Json, _) => writeln!(out, "\n]"),
+            (RecordFormat::Csv, 0) => {
+                let keys: Vec<String> = self.keys.iter().map(|key| key.to_string()).collect();
+                writeln!(out, "{}", csv_row(&keys))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,919 @@
+use std::collections::HashMap;
+use std::fmt;
+
+// A Python object as far as it can be reconstructed without Python. Classes
+// are never imported, instances of them are kept as the class name and the
+// arguments and state they were pickled with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PickleValue {
+    None,
+    Bool(bool),
+    Int(i128),
+    // An int that does not fit into an i128, as its decimal digits.
+    BigInt(String),
+    Float(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    List(Vec<PickleValue>),
+    Tuple(Vec<PickleValue>),
+    Dict(Vec<(PickleValue, PickleValue)>),
+    Set(Vec<PickleValue>),
+    // A reference to a class or function, e.g. collections.OrderedDict.
+    Global(String),
+    Object {
+        class: String,
+        args: Vec<PickleValue>,
+        // Set by SETITEMS on mappings like collections.defaultdict.
+        items: Vec<(PickleValue, PickleValue)>,
+        state: Option<Box<PickleValue>>,
+    },
+    // An object inside itself, e.g. a list that was appended to itself.
+    Recursive,
+}
+
+impl PickleValue {
+    // Looks up a string key in a dict, e.g. an object's __dict__ state.
+    pub fn get(&self, key: &str) -> Option<&PickleValue> {
+        let PickleValue::Dict(items) = self else {
+            return None;
+        };
+        items.iter().find_map(|(k, value)| match k {
+            PickleValue::Str(k) if k == key => Some(value),
+            _ => None,
+        })
+    }
+}
+
+fn write_items(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    items: &[PickleValue],
+    close: &str,
+) -> fmt::Result {
+    write!(f, "{open}")?;
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    write!(f, "{close}")
+}
+
+fn write_dict(f: &mut fmt::Formatter<'_>, items: &[(PickleValue, PickleValue)]) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, (key, value)) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{key}: {value}")?;
+    }
+    write!(f, "}}")
+}
+
+// Roughly what repr() prints in Python.
+impl fmt::Display for PickleValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PickleValue::None => write!(f, "None"),
+            PickleValue::Bool(true) => write!(f, "True"),
+            PickleValue::Bool(false) => write!(f, "False"),
+            PickleValue::Int(i) => write!(f, "{i}"),
+            PickleValue::BigInt(digits) => write!(f, "{digits}"),
+            PickleValue::Float(x) if x.is_finite() && x.fract() == 0.0 && x.abs() < 1e16 => {
+                write!(f, "{x:.1}")
+            }
+            PickleValue::Float(x) => write!(f, "{x}"),
+            PickleValue::Str(s) => write!(f, "{s:?}"),
+            PickleValue::Bytes(b) => write!(f, "b{:?}", String::from_utf8_lossy(b)),
+            PickleValue::List(items) => write_items(f, "[", items, "]"),
+            PickleValue::Tuple(items) if items.len() == 1 => write!(f, "({},)", items[0]),
+            PickleValue::Tuple(items) => write_items(f, "(", items, ")"),
+            PickleValue::Set(items) => write_items(f, "{", items, "}"),
+            PickleValue::Dict(items) => write_dict(f, items),
+            PickleValue::Global(name) => write!(f, "<{name}>"),
+            PickleValue::Recursive => write!(f, "..."),
+            PickleValue::Object {
+                class,
+                args,
+                items,
+                state,
+            } => {
+                write_items(f, &format!("{class}("), args, "")?;
+                if !items.is_empty() {
+                    if !args.is_empty() {
+                        write!(f, ", ")?;
+                    }
+                    write_dict(f, items)?;
+                }
+                write!(f, ")")?;
+                match state {
+                    Some(state) => write!(f, " with state {state}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+// An object while it is being unpickled. Containers refer to their items by
+// index into the unpickler's nodes, so an object that is referenced from the
+// memo or duplicated is shared and sees every item added to it later.
+enum Node {
+    Value(PickleValue),
+    List(Vec<usize>),
+    Tuple(Vec<usize>),
+    Set(Vec<usize>),
+    Dict(Vec<(usize, usize)>),
+    Object {
+        class: String,
+        args: Vec<usize>,
+        items: Vec<(usize, usize)>,
+        state: Option<usize>,
+    },
+}
+
+enum Item {
+    Node(usize),
+    Mark,
+}
+
+// Protocol 0 strings are raw-unicode-escape encoded, that is latin-1 with
+// \uXXXX and \UXXXXXXXX escapes for everything else.
+fn raw_unicode_escape(bytes: &[u8]) -> String {
+    let mut s = String::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let len = match &bytes[i..] {
+            [b'\\', b'u', ..] => 4,
+            [b'\\', b'U', ..] => 8,
+            _ => 0,
+        };
+        let escaped = bytes
+            .get(i + 2..i + 2 + len)
+            .filter(|_| len > 0)
+            .and_then(|hex| u32::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+            .and_then(char::from_u32);
+        match escaped {
+            Some(c) => {
+                s.push(c);
+                i += 2 + len;
+            }
+            None => {
+                s.push(bytes[i] as char);
+                i += 1;
+            }
+        }
+    }
+    s
+}
+
+// Formats a little endian two's complement integer of any size in decimal.
+fn big_int(bytes: &[u8]) -> String {
+    let negative = bytes.last().is_some_and(|b| b & 0x80 != 0);
+    let mut magnitude: Vec<u8> = bytes.iter().rev().copied().collect();
+    if negative {
+        for b in &mut magnitude {
+            *b = !*b;
+        }
+        for b in magnitude.iter_mut().rev() {
+            let (sum, carry) = b.overflowing_add(1);
+            *b = sum;
+            if !carry {
+                break;
+            }
+        }
+    }
+    let mut digits = Vec::new();
+    while magnitude.iter().any(|&b| b != 0) {
+        let mut remainder = 0u32;
+        for b in &mut magnitude {
+            let value = remainder << 8 | *b as u32;
+            *b = (value / 10) as u8;
+            remainder = value % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    if negative {
+        digits.push(b'-');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+// Ints are formatted digit by digit, so larger ones would take too long.
+const MAX_INT_BYTES: usize = 1024;
+
+// How many values a shared object may be copied into at most, as resolving
+// e.g. a list that contains the same list twice, repeatedly, grows
+// exponentially.
+const MAX_RESOLVED_VALUES: usize = 1 << 24;
+
+// Runs the pickle virtual machine over the opcodes of protocols 0 to 5.
+struct Unpickler<'a> {
+    data: &'a [u8],
+    pos: usize,
+    nodes: Vec<Node>,
+    stack: Vec<Item>,
+    memo: HashMap<usize, usize>,
+}
+
+type UResult<T> = Result<T, String>;
+
+impl<'a> Unpickler<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Unpickler {
+            data,
+            pos: 0,
+            nodes: Vec::new(),
+            stack: Vec::new(),
+            memo: HashMap::new(),
+        }
+    }
+
+    fn read(&mut self, n: usize) -> UResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or("unexpected end of data")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> UResult<u8> {
+        Ok(self.read(1)?[0])
+    }
+
+    fn read_uint(&mut self, n: usize) -> UResult<usize> {
+        let bytes = self.read(n)?;
+        let mut value: u64 = 0;
+        for (i, b) in bytes.iter().enumerate() {
+            value |= (*b as u64) << (8 * i);
+        }
+        usize::try_from(value).map_err(|_| "length out of range".to_string())
+    }
+
+    fn read_line_bytes(&mut self) -> UResult<&'a [u8]> {
+        let rest = &self.data[self.pos..];
+        let len = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or("unterminated line")?;
+        self.pos += len + 1;
+        Ok(&rest[..len])
+    }
+
+    fn read_line(&mut self) -> UResult<&'a str> {
+        let line = self.read_line_bytes()?;
+        std::str::from_utf8(line).map_err(|_| "invalid line".to_string())
+    }
+
+    fn read_str(&mut self, len: usize) -> UResult<PickleValue> {
+        let bytes = self.read(len)?;
+        Ok(PickleValue::Str(String::from_utf8_lossy(bytes).to_string()))
+    }
+
+    fn push_node(&mut self, node: Node) {
+        self.nodes.push(node);
+        self.stack.push(Item::Node(self.nodes.len() - 1));
+    }
+
+    fn push(&mut self, value: PickleValue) {
+        self.push_node(Node::Value(value));
+    }
+
+    fn pop(&mut self) -> UResult<usize> {
+        match self.stack.pop() {
+            Some(Item::Node(id)) => Ok(id),
+            Some(Item::Mark) => Err("unexpected mark".to_string()),
+            None => Err("stack underflow".to_string()),
+        }
+    }
+
+    fn top(&self) -> UResult<usize> {
+        match self.stack.last() {
+            Some(Item::Node(id)) => Ok(*id),
+            _ => Err("stack underflow".to_string()),
+        }
+    }
+
+    // Pops everything up to and including the topmost mark.
+    fn pop_mark(&mut self) -> UResult<Vec<usize>> {
+        let mark = self
+            .stack
+            .iter()
+            .rposition(|item| matches!(item, Item::Mark))
+            .ok_or("missing mark")?;
+        let items = self.stack.split_off(mark + 1);
+        self.stack.pop();
+        Ok(items
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::Node(id) => Some(id),
+                Item::Mark => None,
+            })
+            .collect())
+    }
+
+    // Values that are read from the data rather than built up by opcodes.
+    fn value(&self, id: usize) -> Option<&PickleValue> {
+        match &self.nodes[id] {
+            Node::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn pop_tuple(&mut self) -> UResult<Vec<usize>> {
+        let id = self.pop()?;
+        match &self.nodes[id] {
+            Node::Tuple(items) => Ok(items.clone()),
+            _ => Err("arguments are not a tuple".to_string()),
+        }
+    }
+
+    fn memoize(&mut self, index: usize) -> UResult<()> {
+        let id = self.top()?;
+        self.memo.insert(index, id);
+        Ok(())
+    }
+
+    fn get(&mut self, index: usize) -> UResult<()> {
+        let id = *self.memo.get(&index).ok_or("unknown memo key")?;
+        self.stack.push(Item::Node(id));
+        Ok(())
+    }
+
+    fn extend(&mut self, items: Vec<usize>) -> UResult<()> {
+        let top = self.top()?;
+        match &mut self.nodes[top] {
+            Node::List(list) | Node::Set(list) => list.extend(items),
+            Node::Object { args, .. } => args.extend(items),
+            _ => return Err("append to a non-list".to_string()),
+        }
+        Ok(())
+    }
+
+    fn set_items(&mut self, items: Vec<usize>) -> UResult<()> {
+        let top = self.top()?;
+        let (Node::Dict(dict) | Node::Object { items: dict, .. }) = &mut self.nodes[top] else {
+            return Err("setitem on a non-dict".to_string());
+        };
+        let mut items = items.into_iter();
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            dict.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn global(&mut self, module: &str, name: &str) {
+        // Protocols 0 to 2 use the Python 2 names of builtin modules.
+        let module = match module {
+            "__builtin__" => "builtins",
+            "copy_reg" => "copyreg",
+            module => module,
+        };
+        self.push(PickleValue::Global(format!("{module}.{name}")));
+    }
+
+    // Calls a class or function. Only a few builtins are reconstructed.
+    fn call(&self, callable: usize, args: Vec<usize>) -> UResult<Node> {
+        let class = match self.value(callable) {
+            Some(PickleValue::Global(class)) => class.clone(),
+            _ => self.resolve(callable)?.to_string(),
+        };
+        let arg = |i: usize| args.get(i).map(|&id| &self.nodes[id]);
+        let node = match (class.as_str(), args.len(), arg(0), arg(1)) {
+            ("builtins.set" | "builtins.frozenset", 1, Some(Node::List(items)), _) => {
+                Node::Set(items.clone())
+            }
+            ("collections.OrderedDict", 0, ..) => Node::Dict(Vec::new()),
+            ("builtins.bytearray", 1, Some(Node::Value(bytes @ PickleValue::Bytes(_))), _) => {
+                Node::Value(bytes.clone())
+            }
+            // Protocols 0 to 2 pickle bytes as the string they decode to.
+            (
+                "_codecs.encode",
+                2,
+                Some(Node::Value(PickleValue::Str(s))),
+                Some(Node::Value(PickleValue::Str(encoding))),
+            ) if encoding == "latin1" => {
+                Node::Value(PickleValue::Bytes(s.chars().map(|c| c as u8).collect()))
+            }
+            // Instances pickled with protocols 0 and 1.
+            ("copyreg._reconstructor", 3, Some(Node::Value(PickleValue::Global(class))), _) => {
+                Node::Object {
+                    class: class.clone(),
+                    args: Vec::new(),
+                    items: Vec::new(),
+                    state: None,
+                }
+            }
+            _ => Node::Object {
+                class,
+                args,
+                items: Vec::new(),
+                state: None,
+            },
+        };
+        Ok(node)
+    }
+
+    // Turns a node into a value once all opcodes ran. Objects that contain
+    // themselves are cut off where they recur.
+    fn resolve(&self, id: usize) -> UResult<PickleValue> {
+        let mut budget = MAX_RESOLVED_VALUES;
+        self.resolve_in(id, &mut Vec::new(), &mut budget)
+    }
+
+    fn resolve_in(
+        &self,
+        id: usize,
+        path: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> UResult<PickleValue> {
+        if path.contains(&id) {
+            return Ok(PickleValue::Recursive);
+        }
+        *budget = budget.checked_sub(1).ok_or("too many shared references")?;
+        path.push(id);
+        let value = match &self.nodes[id] {
+            Node::Value(value) => value.clone(),
+            Node::List(items) => PickleValue::List(self.resolve_all(items, path, budget)?),
+            Node::Tuple(items) => PickleValue::Tuple(self.resolve_all(items, path, budget)?),
+            Node::Set(items) => PickleValue::Set(self.resolve_all(items, path, budget)?),
+            Node::Dict(items) => PickleValue::Dict(self.resolve_pairs(items, path, budget)?),
+            Node::Object {
+                class,
+                args,
+                items,
+                state,
+            } => PickleValue::Object {
+                class: class.clone(),
+                args: self.resolve_all(args, path, budget)?,
+                items: self.resolve_pairs(items, path, budget)?,
+                state: match state {
+                    Some(state) => Some(Box::new(self.resolve_in(*state, path, budget)?)),
+                    None => None,
+                },
+            },
+        };
+        path.pop();
+        Ok(value)
+    }
+
+    fn resolve_all(
+        &self,
+        ids: &[usize],
+        path: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> UResult<Vec<PickleValue>> {
+        ids.iter()
+            .map(|&id| self.resolve_in(id, path, budget))
+            .collect()
+    }
+
+    fn resolve_pairs(
+        &self,
+        pairs: &[(usize, usize)],
+        path: &mut Vec<usize>,
+        budget: &mut usize,
+    ) -> UResult<Vec<(PickleValue, PickleValue)>> {
+        pairs
+            .iter()
+            .map(|&(key, value)| {
+                Ok((
+                    self.resolve_in(key, path, budget)?,
+                    self.resolve_in(value, path, budget)?,
+                ))
+            })
+            .collect()
+    }
+
+    // Executes a single opcode, returns the unpickled object once done.
+    fn step(&mut self) -> UResult<Option<PickleValue>> {
+        let op = self.read_u8()?;
+        match op {
+            0x80 => {
+                self.read_u8()?;
+            }
+            0x95 => {
+                self.read(8)?;
+            }
+            b'.' => {
+                let id = self.pop()?;
+                return self.resolve(id).map(Some);
+            }
+            b'(' => self.stack.push(Item::Mark),
+            b'0' => {
+                self.stack.pop();
+            }
+            b'1' => {
+                self.pop_mark()?;
+            }
+            b'2' => {
+                let id = self.top()?;
+                self.stack.push(Item::Node(id));
+            }
+            b'N' => self.push(PickleValue::None),
+            0x88 => self.push(PickleValue::Bool(true)),
+            0x89 => self.push(PickleValue::Bool(false)),
+            b'I' => {
+                let value = match self.read_line()? {
+                    "00" => PickleValue::Bool(false),
+                    "01" => PickleValue::Bool(true),
+                    line => PickleValue::Int(line.parse().map_err(|_| "invalid int")?),
+                };
+                self.push(value);
+            }
+            b'J' => {
+                let bytes = self.read(4)?;
+                let value = i32::from_le_bytes(bytes.try_into().unwrap());
+                self.push(PickleValue::Int(value as i128));
+            }
+            b'K' => {
+                let value = self.read_u8()?;
+                self.push(PickleValue::Int(value as i128));
+            }
+            b'M' => {
+                let value = self.read_uint(2)?;
+                self.push(PickleValue::Int(value as i128));
+            }
+            b'L' => {
+                let line = self.read_line()?.trim_end_matches('L');
+                let digits = line.strip_prefix('-').unwrap_or(line);
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err("invalid long".to_string());
+                }
+                let value = match line.parse() {
+                    Ok(value) => PickleValue::Int(value),
+                    Err(_) => PickleValue::BigInt(line.to_string()),
+                };
+                self.push(value);
+            }
+            0x8a | 0x8b => {
+                let len = if op == 0x8a {
+                    self.read_u8()? as usize
+                } else {
+                    self.read_uint(4)?
+                };
+                let bytes = self.read(len)?;
+                if len > MAX_INT_BYTES {
+                    return Err("integer too large".to_string());
+                }
+                if len > 16 {
+                    self.push(PickleValue::BigInt(big_int(bytes)));
+                    return Ok(None);
+                }
+                // Little endian two's complement.
+                let mut value: i128 = 0;
+                for (i, b) in bytes.iter().enumerate() {
+                    value |= (*b as i128) << (8 * i);
+                }
+                if len > 0 && len < 16 && bytes[len - 1] & 0x80 != 0 {
+                    value -= 1 << (8 * len);
+                }
+                self.push(PickleValue::Int(value));
+            }
+            b'F' => {
+                let line = self.read_line()?;
+                self.push(PickleValue::Float(
+                    line.parse().map_err(|_| "invalid float")?,
+                ));
+            }
+            b'G' => {
+                let bytes = self.read(8)?;
+                self.push(PickleValue::Float(f64::from_be_bytes(
+                    bytes.try_into().unwrap(),
+                )));
+            }
+            b'S' => {
+                let line = self.read_line()?;
+                let value = line.trim_matches(|c| c == '\'' || c == '"').to_string();
+                self.push(PickleValue::Str(value));
+            }
+            b'V' => {
+                let line = self.read_line_bytes()?;
+                self.push(PickleValue::Str(raw_unicode_escape(line)));
+            }
+            b'T' | b'X' => {
+                let len = self.read_uint(4)?;
+                let value = self.read_str(len)?;
+                self.push(value);
+            }
+            b'U' | 0x8c => {
+                let len = self.read_u8()? as usize;
+                let value = self.read_str(len)?;
+                self.push(value);
+            }
+            0x8d => {
+                let len = self.read_uint(8)?;
+                let value = self.read_str(len)?;
+                self.push(value);
+            }
+            b'B' | b'C' | 0x8e | 0x96 => {
+                let len = match op {
+                    b'C' => self.read_u8()? as usize,
+                    b'B' => self.read_uint(4)?,
+                    _ => self.read_uint(8)?,
+                };
+                let bytes = self.read(len)?.to_vec();
+                self.push(PickleValue::Bytes(bytes));
+            }
+            b']' => self.push_node(Node::List(Vec::new())),
+            b'a' => {
+                let value = self.pop()?;
+                self.extend(vec![value])?;
+            }
+            b'e' => {
+                let items = self.pop_mark()?;
+                self.extend(items)?;
+            }
+            b'l' => {
+                let items = self.pop_mark()?;
+                self.push_node(Node::List(items));
+            }
+            b')' => self.push_node(Node::Tuple(Vec::new())),
+            b't' => {
+                let items = self.pop_mark()?;
+                self.push_node(Node::Tuple(items));
+            }
+            0x85..=0x87 => {
+                let n = (op - 0x84) as usize;
+                let mut items = Vec::with_capacity(n);
+                for _ in 0..n {
+                    items.push(self.pop()?);
+                }
+                items.reverse();
+                self.push_node(Node::Tuple(items));
+            }
+            b'}' => self.push_node(Node::Dict(Vec::new())),
+            b'd' => {
+                let items = self.pop_mark()?;
+                self.push_node(Node::Dict(Vec::new()));
+                self.set_items(items)?;
+            }
+            b's' => {
+                let value = self.pop()?;
+                let key = self.pop()?;
+                self.set_items(vec![key, value])?;
+            }
+            b'u' => {
+                let items = self.pop_mark()?;
+                self.set_items(items)?;
+            }
+            0x8f => self.push_node(Node::Set(Vec::new())),
+            0x90 => {
+                let items = self.pop_mark()?;
+                self.extend(items)?;
+            }
+            0x91 => {
+                let items = self.pop_mark()?;
+                self.push_node(Node::Set(items));
+            }
+            b'c' => {
+                let module = self.read_line()?;
+                let name = self.read_line()?;
+                self.global(module, name);
+            }
+            0x93 => {
+                let name = self.pop()?;
+                let module = self.pop()?;
+                let (Some(PickleValue::Str(module)), Some(PickleValue::Str(name))) =
+                    (self.value(module), self.value(name))
+                else {
+                    return Err("invalid global".to_string());
+                };
+                let (module, name) = (module.clone(), name.clone());
+                self.global(&module, &name);
+            }
+            b'R' | 0x81 => {
+                let args = self.pop_tuple()?;
+                let callable = self.pop()?;
+                let node = self.call(callable, args)?;
+                self.push_node(node);
+            }
+            0x92 => {
+                let kwargs = self.pop()?;
+                let args = self.pop_tuple()?;
+                let callable = self.pop()?;
+                let mut node = self.call(callable, args)?;
+                if let Node::Object { args, .. } = &mut node {
+                    args.push(kwargs);
+                }
+                self.push_node(node);
+            }
+            b'o' => {
+                let mut items = self.pop_mark()?;
+                if items.is_empty() {
+                    return Err("missing class".to_string());
+                }
+                let callable = items.remove(0);
+                let node = self.call(callable, items)?;
+                self.push_node(node);
+            }
+            b'i' => {
+                let module = self.read_line()?;
+                let name = self.read_line()?;
+                let args = self.pop_mark()?;
+                self.global(module, name);
+                let callable = self.pop()?;
+                let node = self.call(callable, args)?;
+                self.push_node(node);
+            }
+            b'b' => {
+                let new_state = self.pop()?;
+                let top = self.top()?;
+                let new_items = match &self.nodes[new_state] {
+                    Node::Dict(items) => items.clone(),
+                    _ => Vec::new(),
+                };
+                match &mut self.nodes[top] {
+                    Node::Object { state, .. } => *state = Some(new_state),
+                    Node::Dict(dict) => dict.extend(new_items),
+                    _ => {}
+                }
+            }
+            b'p' => {
+                let index = self.read_line()?.parse().map_err(|_| "invalid memo key")?;
+                self.memoize(index)?;
+            }
+            b'q' => {
+                let index = self.read_u8()? as usize;
+                self.memoize(index)?;
+            }
+            b'r' => {
+                let index = self.read_uint(4)?;
+                self.memoize(index)?;
+            }
+            0x94 => {
+                let index = self.memo.len();
+                self.memoize(index)?;
+            }
+            b'g' => {
+                let index = self.read_line()?.parse().map_err(|_| "invalid memo key")?;
+                self.get(index)?;
+            }
+            b'h' => {
+                let index = self.read_u8()? as usize;
+                self.get(index)?;
+            }
+            b'j' => {
+                let index = self.read_uint(4)?;
+                self.get(index)?;
+            }
+            op => return Err(format!("unsupported opcode 0x{op:02x}")),
+        }
+        Ok(None)
+    }
+}
+
+pub fn loads(data: &[u8]) -> Result<PickleValue, String> {
+    let mut unpickler = Unpickler::new(data);
+    loop {
+        if let Some(value) = unpickler.step()? {
+            return Ok(value);
+        }
+    }
+}
+
+// The first string in the pickle, which only needs the start of the data.
+pub fn first_string(data: &[u8]) -> Option<String> {
+    let mut unpickler = Unpickler::new(data);
+    loop {
+        unpickler.step().ok()?;
+        let top = unpickler.top().ok();
+        if let Some(PickleValue::Str(s)) = top.and_then(|id| unpickler.value(id)) {
+            return Some(s.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pickled by CPython with protocols 0 to 5.
+    const SHARED: [&[u8]; 6] = [
+        b"(Vsuccess\np0\n(dp1\nVa\np2\n(lp3\nI1\naI2\naI3\nasVb\np4\ng3\nstp5\n.",
+        b"(X\x07\x00\x00\x00successq\x00}q\x01(X\x01\x00\x00\x00aq\x02]q\x03(K\x01K\x02K\x03eX\x01\x00\x00\x00bq\x04h\x03utq\x05.",
+        b"\x80\x02X\x07\x00\x00\x00successq\x00}q\x01(X\x01\x00\x00\x00aq\x02]q\x03(K\x01K\x02K\x03eX\x01\x00\x00\x00bq\x04h\x03u\x86q\x05.",
+        b"\x80\x03X\x07\x00\x00\x00successq\x00}q\x01(X\x01\x00\x00\x00aq\x02]q\x03(K\x01K\x02K\x03eX\x01\x00\x00\x00bq\x04h\x03u\x86q\x05.",
+        b"\x80\x04\x95%\x00\x00\x00\x00\x00\x00\x00\x8c\x07success\x94}\x94(\x8c\x01a\x94]\x94(K\x01K\x02K\x03e\x8c\x01b\x94h\x03u\x86\x94.",
+        b"\x80\x05\x95%\x00\x00\x00\x00\x00\x00\x00\x8c\x07success\x94}\x94(\x8c\x01a\x94]\x94(K\x01K\x02K\x03e\x8c\x01b\x94h\x03u\x86\x94.",
+    ];
+
+    const NESTED: [&[u8]; 6] = [
+        b"(dp0\nVa\np1\n(lp2\nI1\na(I2\nI3\ntp3\na(dp4\nVb\np5\nc__builtin__\nset\np6\n((lp7\nI4\natp8\nRp9\nsasVc\np10\n(NI01\nF1.5\ntp11\nsVd\np12\nc_codecs\nencode\np13\n(Vab\np14\nVlatin1\np15\ntp16\nRp17\nsVe\np18\n(I5\ntp19\nsVf\np20\nV\xe9\\u000a\\u005c\np21\ns.",
+        b"}q\x00(X\x01\x00\x00\x00aq\x01]q\x02(K\x01(K\x02K\x03tq\x03}q\x04X\x01\x00\x00\x00bq\x05c__builtin__\nset\nq\x06(]q\x07K\x04atq\x08Rq\x09seX\x01\x00\x00\x00cq\n(NI01\nG?\xf8\x00\x00\x00\x00\x00\x00tq\x0bX\x01\x00\x00\x00dq\x0cc_codecs\nencode\nq\x0d(X\x02\x00\x00\x00abq\x0eX\x06\x00\x00\x00latin1q\x0ftq\x10Rq\x11X\x01\x00\x00\x00eq\x12(K\x05tq\x13X\x01\x00\x00\x00fq\x14X\x04\x00\x00\x00\xc3\xa9\n\\q\x15u.",
+        b"\x80\x02}q\x00(X\x01\x00\x00\x00aq\x01]q\x02(K\x01K\x02K\x03\x86q\x03}q\x04X\x01\x00\x00\x00bq\x05c__builtin__\nset\nq\x06]q\x07K\x04a\x85q\x08Rq\x09seX\x01\x00\x00\x00cq\nN\x88G?\xf8\x00\x00\x00\x00\x00\x00\x87q\x0bX\x01\x00\x00\x00dq\x0cc_codecs\nencode\nq\x0dX\x02\x00\x00\x00abq\x0eX\x06\x00\x00\x00latin1q\x0f\x86q\x10Rq\x11X\x01\x00\x00\x00eq\x12K\x05\x85q\x13X\x01\x00\x00\x00fq\x14X\x04\x00\x00\x00\xc3\xa9\n\\q\x15u.",
+        b"\x80\x03}q\x00(X\x01\x00\x00\x00aq\x01]q\x02(K\x01K\x02K\x03\x86q\x03}q\x04X\x01\x00\x00\x00bq\x05cbuiltins\nset\nq\x06]q\x07K\x04a\x85q\x08Rq\x09seX\x01\x00\x00\x00cq\nN\x88G?\xf8\x00\x00\x00\x00\x00\x00\x87q\x0bX\x01\x00\x00\x00dq\x0cC\x02abq\x0dX\x01\x00\x00\x00eq\x0eK\x05\x85q\x0fX\x01\x00\x00\x00fq\x10X\x04\x00\x00\x00\xc3\xa9\n\\q\x11u.",
+        b"\x80\x04\x95O\x00\x00\x00\x00\x00\x00\x00}\x94(\x8c\x01a\x94]\x94(K\x01K\x02K\x03\x86\x94}\x94\x8c\x01b\x94\x8f\x94(K\x04\x90se\x8c\x01c\x94N\x88G?\xf8\x00\x00\x00\x00\x00\x00\x87\x94\x8c\x01d\x94C\x02ab\x94\x8c\x01e\x94K\x05\x85\x94\x8c\x01f\x94\x8c\x04\xc3\xa9\n\\\x94u.",
+        b"\x80\x05\x95O\x00\x00\x00\x00\x00\x00\x00}\x94(\x8c\x01a\x94]\x94(K\x01K\x02K\x03\x86\x94}\x94\x8c\x01b\x94\x8f\x94(K\x04\x90se\x8c\x01c\x94N\x88G?\xf8\x00\x00\x00\x00\x00\x00\x87\x94\x8c\x01d\x94C\x02ab\x94\x8c\x01e\x94K\x05\x85\x94\x8c\x01f\x94\x8c\x04\xc3\xa9\n\\\x94u.",
+    ];
+
+    const DEFAULTDICT: [&[u8]; 6] = [
+        b"ccollections\ndefaultdict\np0\n(c__builtin__\nlist\np1\ntp2\nRp3\nVa\np4\n(lp5\nI1\nas.",
+        b"ccollections\ndefaultdict\nq\x00(c__builtin__\nlist\nq\x01tq\x02Rq\x03X\x01\x00\x00\x00aq\x04]q\x05K\x01as.",
+        b"\x80\x02ccollections\ndefaultdict\nq\x00c__builtin__\nlist\nq\x01\x85q\x02Rq\x03X\x01\x00\x00\x00aq\x04]q\x05K\x01as.",
+        b"\x80\x03ccollections\ndefaultdict\nq\x00cbuiltins\nlist\nq\x01\x85q\x02Rq\x03X\x01\x00\x00\x00aq\x04]q\x05K\x01as.",
+        b"\x80\x04\x95A\x00\x00\x00\x00\x00\x00\x00\x8c\x0bcollections\x94\x8c\x0bdefaultdict\x94\x93\x94\x8c\x08builtins\x94\x8c\x04list\x94\x93\x94\x85\x94R\x94\x8c\x01a\x94]\x94K\x01as.",
+        b"\x80\x05\x95A\x00\x00\x00\x00\x00\x00\x00\x8c\x0bcollections\x94\x8c\x0bdefaultdict\x94\x93\x94\x8c\x08builtins\x94\x8c\x04list\x94\x93\x94\x85\x94R\x94\x8c\x01a\x94]\x94K\x01as.",
+    ];
+
+    const ORDERED_DICT: [&[u8]; 6] = [
+        b"ccollections\nOrderedDict\np0\n(tRp1\nVx\np2\nI1\nsVy\np3\nI2\ns.",
+        b"ccollections\nOrderedDict\nq\x00)Rq\x01(X\x01\x00\x00\x00xq\x02K\x01X\x01\x00\x00\x00yq\x03K\x02u.",
+        b"\x80\x02ccollections\nOrderedDict\nq\x00)Rq\x01(X\x01\x00\x00\x00xq\x02K\x01X\x01\x00\x00\x00yq\x03K\x02u.",
+        b"\x80\x03ccollections\nOrderedDict\nq\x00)Rq\x01(X\x01\x00\x00\x00xq\x02K\x01X\x01\x00\x00\x00yq\x03K\x02u.",
+        b"\x80\x04\x950\x00\x00\x00\x00\x00\x00\x00\x8c\x0bcollections\x94\x8c\x0bOrderedDict\x94\x93\x94)R\x94(\x8c\x01x\x94K\x01\x8c\x01y\x94K\x02u.",
+        b"\x80\x05\x950\x00\x00\x00\x00\x00\x00\x00\x8c\x0bcollections\x94\x8c\x0bOrderedDict\x94\x93\x94)R\x94(\x8c\x01x\x94K\x01\x8c\x01y\x94K\x02u.",
+    ];
+
+    const LARGE_INTS: [&[u8]; 6] = [
+        b"(lp0\nL1267650600228229401496703205376L\naL-1180591620717411303424L\naL2147483648L\naI-1\naI70000\naL1606938044258990275541962092341162602522202993782792835301376L\naL-1606938044258990275541962092341162602522202993782792835301376L\na.",
+        b"]q\x00(L1267650600228229401496703205376L\nL-1180591620717411303424L\nL2147483648L\nJ\xff\xff\xff\xffJp\x11\x01\x00L1606938044258990275541962092341162602522202993782792835301376L\nL-1606938044258990275541962092341162602522202993782792835301376L\ne.",
+        b"\x80\x02]q\x00(\x8a\x0d\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\x8a\x09\x00\x00\x00\x00\x00\x00\x00\x00\xc0\x8a\x05\x00\x00\x00\x80\x00J\xff\xff\xff\xffJp\x11\x01\x00\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xffe.",
+        b"\x80\x03]q\x00(\x8a\x0d\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\x8a\x09\x00\x00\x00\x00\x00\x00\x00\x00\xc0\x8a\x05\x00\x00\x00\x80\x00J\xff\xff\xff\xffJp\x11\x01\x00\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xffe.",
+        b"\x80\x04\x95h\x00\x00\x00\x00\x00\x00\x00]\x94(\x8a\x0d\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\x8a\x09\x00\x00\x00\x00\x00\x00\x00\x00\xc0\x8a\x05\x00\x00\x00\x80\x00J\xff\xff\xff\xffJp\x11\x01\x00\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xffe.",
+        b"\x80\x05\x95h\x00\x00\x00\x00\x00\x00\x00]\x94(\x8a\x0d\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x10\x8a\x09\x00\x00\x00\x00\x00\x00\x00\x00\xc0\x8a\x05\x00\x00\x00\x80\x00J\xff\xff\xff\xffJp\x11\x01\x00\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x8a\x1a\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\xffe.",
+    ];
+
+    const POINT: [&[u8]; 6] = [
+        b"ccopy_reg\n_reconstructor\np0\n(c__main__\nPoint\np1\nc__builtin__\nobject\np2\nNtp3\nRp4\n(dp5\nVx\np6\nI1\nsVy\np7\nI2\nsb.",
+        b"ccopy_reg\n_reconstructor\nq\x00(c__main__\nPoint\nq\x01c__builtin__\nobject\nq\x02Ntq\x03Rq\x04}q\x05(X\x01\x00\x00\x00xq\x06K\x01X\x01\x00\x00\x00yq\x07K\x02ub.",
+        b"\x80\x02c__main__\nPoint\nq\x00)\x81q\x01}q\x02(X\x01\x00\x00\x00xq\x03K\x01X\x01\x00\x00\x00yq\x04K\x02ub.",
+        b"\x80\x03c__main__\nPoint\nq\x00)\x81q\x01}q\x02(X\x01\x00\x00\x00xq\x03K\x01X\x01\x00\x00\x00yq\x04K\x02ub.",
+        b"\x80\x04\x95*\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x05Point\x94\x93\x94)\x81\x94}\x94(\x8c\x01x\x94K\x01\x8c\x01y\x94K\x02ub.",
+        b"\x80\x05\x95*\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x05Point\x94\x93\x94)\x81\x94}\x94(\x8c\x01x\x94K\x01\x8c\x01y\x94K\x02ub.",
+    ];
+
+    const SLOTS: [&[u8]; 4] = [
+        b"\x80\x02c__main__\nSlotted\nq\x00)\x81q\x01N}q\x02X\x01\x00\x00\x00xq\x03K\x01s\x86q\x04b.",
+        b"\x80\x03c__main__\nSlotted\nq\x00)\x81q\x01N}q\x02X\x01\x00\x00\x00xq\x03K\x01s\x86q\x04b.",
+        b"\x80\x04\x95(\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x07Slotted\x94\x93\x94)\x81\x94N}\x94\x8c\x01x\x94K\x01s\x86\x94b.",
+        b"\x80\x05\x95(\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x07Slotted\x94\x93\x94)\x81\x94N}\x94\x8c\x01x\x94K\x01s\x86\x94b.",
+    ];
+
+    const NEWOBJ_EX: [&[u8]; 2] = [
+        b"\x80\x04\x95,\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x02Kw\x94\x93\x94K\x01\x85\x94}\x94\x8c\x01b\x94K\x02s\x92\x94}\x94\x8c\x01a\x94K\x01sb.",
+        b"\x80\x05\x95,\x00\x00\x00\x00\x00\x00\x00\x8c\x08__main__\x94\x8c\x02Kw\x94\x93\x94K\x01\x85\x94}\x94\x8c\x01b\x94K\x02s\x92\x94}\x94\x8c\x01a\x94K\x01sb.",
+    ];
+
+    fn assert_loads(fixtures: &[&[u8]], expected: &str) {
+        for (protocol, data) in fixtures.iter().enumerate() {
+            let value = loads(data).unwrap_or_else(|e| panic!("protocol {protocol}: {e}"));
+            assert_eq!(value.to_string(), expected, "protocol {protocol}");
+        }
+    }
+
+    #[test]
+    fn shared_references_see_later_items() {
+        // The list is memoized before its items are appended.
+        assert_loads(&SHARED, r#"("success", {"a": [1, 2, 3], "b": [1, 2, 3]})"#);
+    }
+
+    #[test]
+    fn recursive_list() {
+        assert_loads(&[b"\x80\x02]q\x00(K\x01h\x00e."], "[1, ...]");
+    }
+    #[test]
+    fn nested_containers() {
+        assert_loads(
+            &NESTED,
+            r#"{"a": [1, (2, 3), {"b": {4}}], "c": (None, True, 1.5), "d": b"ab", "e": (5,), "f": "é\n\\"}"#,
+        );
+    }
+
+    #[test]
+    fn defaultdict_keeps_its_items() {
+        assert_loads(
+            &DEFAULTDICT,
+            r#"collections.defaultdict(<builtins.list>, {"a": [1]})"#,
+        );
+    }
+
+    #[test]
+    fn ordered_dict() {
+        assert_loads(&ORDERED_DICT, r#"{"x": 1, "y": 2}"#);
+    }
+
+    #[test]
+    fn large_ints() {
+        assert_loads(&LARGE_INTS, "[1267650600228229401496703205376, -1180591620717411303424, 2147483648, -1, 70000, 1606938044258990275541962092341162602522202993782792835301376, -1606938044258990275541962092341162602522202993782792835301376]");
+    }
+
+    #[test]
+    fn objects_keep_their_state() {
+        assert_loads(&POINT, r#"__main__.Point() with state {"x": 1, "y": 2}"#);
+        assert_loads(&SLOTS, r#"__main__.Slotted() with state (None, {"x": 1})"#);
+        assert_loads(
+            &NEWOBJ_EX,
+            r#"__main__.Kw(1, {"b": 2}) with state {"a": 1}"#,
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use colored::{Color, Colorize};
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+// Markers and colors are cycled through, so that series can still be told
+// apart without colors.
+const MARKERS: [char; 6] = ['●', '■', '▲', '◆', '+', 'x'];
+const COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Green,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+];
+
+pub const PLOT_WIDTH: usize = 64;
+pub const PLOT_HEIGHT: usize = 16;
+pub const SPARK_WIDTH: usize = 24;
+
+// Smallest and largest value, widened if all values are the same so that
+// there is something to scale by.
+fn value_range<'a>(values: impl Iterator<Item = &'a f64>) -> Option<(f64, f64)> {
+    let (min, max) = values
+        .filter(|value| value.is_finite())
+        .fold(None, |range, &value| match range {
+            None => Some((value, value)),
+            Some((min, max)) => Some((f64::min(min, value), f64::max(max, value))),
+        })?;
+    if min == max {
+        return Some((min - 0.5, max + 0.5));
+    }
+    Some((min, max))
+}
+
+// A one line summary of a series like ▇▅▄▃▂▁▁, at most max_width characters
+// wide. Longer series are resampled.
+pub fn sparkline(values: &[f64], max_width: usize) -> String {
+    let Some((min, max)) = value_range(values.iter()) else {
+        return String::new();
+    };
+    let width = values.len().min(max_width);
+    (0..width)
+        .map(|i| values[i * values.len() / width])
+        .map(|value| {
+            if !value.is_finite() {
+                return ' ';
+            }
+            let level = (value - min) / (max - min) * (SPARK_LEVELS.len() - 1) as f64;
+            SPARK_LEVELS[level.round() as usize]
+        })
+        .collect()
+}
+
+pub fn marker(series: usize) -> String {
+    MARKERS[series % MARKERS.len()]
+        .to_string()
+        .color(COLORS[series % COLORS.len()])
+        .to_string()
+}
+
+// Draws the series into a width x height grid, with the steps on the x axis.
+// Consecutive values are connected, where series overlap the later one wins.
+pub fn line_plot(series: &[Vec<f64>], width: usize, height: usize) -> Vec<String> {
+    let Some((min, max)) = value_range(series.iter().flatten()) else {
+        return Vec::new();
+    };
+    let len = series.iter().map(Vec::len).max().unwrap_or(0);
+    let steps = len.max(2);
+    let x = |step: usize| step * (width - 1) / (steps - 1);
+    let y = |value: f64| ((max - value) / (max - min) * (height - 1) as f64).round() as usize;
+
+    let mut grid: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+    for (s, values) in series.iter().enumerate() {
+        let points: Vec<(usize, f64)> = values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| value.is_finite())
+            .map(|(step, value)| (x(step), *value))
+            .collect();
+        let mut draw = |col: usize, value: f64| grid[y(value)][col] = Some(s);
+        for pair in points.windows(2) {
+            let [(x0, v0), (x1, v1)] = [pair[0], pair[1]];
+            for col in x0..=x1 {
+                let t = if x1 == x0 {
+                    1.0
+                } else {
+                    (col - x0) as f64 / (x1 - x0) as f64
+                };
+                draw(col, v0 + t * (v1 - v0));
+            }
+        }
+        if let [(col, value)] = points[..] {
+            draw(col, value);
+        }
+    }
+
+    let labels: Vec<String> = (0..height)
+        .map(|row| match row {
+            0 => format!("{max:.4}"),
+            row if row == height - 1 => format!("{min:.4}"),
+            _ => String::new(),
+        })
+        .collect();
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+    let mut lines: Vec<String> = grid
+        .iter()
+        .zip(&labels)
+        .map(|(row, label)| {
+            let cells: String = row
+                .iter()
+                .map(|cell| match cell {
+                    Some(s) => marker(*s),
+                    None => " ".to_string(),
+                })
+                .collect();
+            format!("{label:>label_width$} ┤{cells}")
+        })
+        .collect();
+    lines.push(format!("{:>label_width$} └{}", "", "─".repeat(width)));
+    let last_step = len.saturating_sub(1).to_string();
+    lines.push(format!(
+        "{:>label_width$}  0{last_step:>w$}",
+        "",
+        w = width - 1
+    ));
+    lines
+}
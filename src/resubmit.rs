@@ -0,0 +1,212 @@
+use std::path::PathBuf;
+
+// Hydra's hydra.sweep.dir, for sweeps whose hydra.yaml does not have it.
+pub const DEFAULT_SWEEP_DIR: &str = "multirun/${now:%Y-%m-%d}/${now:%H-%M-%S}";
+
+// A Hydra multirun launch that reruns jobs with their original overrides.
+pub struct Rerun {
+    // Where the sweep was launched from, as the script may load its config
+    // relative to it.
+    pub cwd: Option<PathBuf>,
+    pub script: String,
+    pub overrides: Vec<String>,
+    // The unresolved hydra.sweep.dir of the original sweep.
+    pub sweep_dir: String,
+}
+
+impl Rerun {
+    // Launches started in the same second would share a sweep directory
+    // named after the time, and overwrite each other's multirun.yaml and
+    // .hydra files. Numbering the directory keeps them apart.
+    pub fn number_sweep_dir(&mut self, n: usize) {
+        self.overrides
+            .retain(|o| !o.starts_with("hydra.sweep.dir="));
+        self.overrides
+            .push(format!("hydra.sweep.dir={}_{n}", self.sweep_dir));
+    }
+
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec![self.script.clone(), "-m".to_string()];
+        args.extend(self.overrides.iter().cloned());
+        args
+    }
+
+    pub fn shell_command(&self, python: &str) -> String {
+        let mut command: Vec<String> = std::iter::once(python.to_string())
+            .chain(self.args())
+            .map(|arg| shell_quote(&arg))
+            .collect();
+        if let Some(cwd) = &self.cwd {
+            command.insert(
+                0,
+                format!("cd {} &&", shell_quote(&cwd.display().to_string())),
+            );
+        }
+        command.join(" ")
+    }
+}
+
+// Overrides like model.name='resnet 18' or +tags=[a,b] need quotes in a shell.
+fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./=+:@%,".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        return s.to_string();
+    }
+    // Hydra quotes strings with single quotes, which read better in double
+    // quotes than escaped.
+    if s.contains('\'') && !s.contains(['"', '$', '`', '\\', '!']) {
+        return format!("\"{s}\"");
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn override_key(o: &str) -> &str {
+    o.split_once('=').map_or(o, |(key, _)| key)
+}
+
+// Merges the overrides of several jobs into a single sweep like
+// lr=0.1,0.01 model=a,b. This is only possible if the jobs are every
+// combination of the values, otherwise Hydra would also launch jobs that
+// did not fail. Returns None if the jobs cannot be merged.
+pub fn merge_overrides(jobs: &[Vec<String>]) -> Option<Vec<String>> {
+    let mut jobs: Vec<&Vec<String>> = jobs.iter().collect();
+    jobs.sort();
+    jobs.dedup();
+    let first = jobs.first()?;
+    let same_keys = |job: &&Vec<String>| {
+        job.len() == first.len()
+            && job
+                .iter()
+                .zip(first.iter())
+                .all(|(a, b)| override_key(a) == override_key(b))
+    };
+    if !jobs.iter().all(same_keys) {
+        return None;
+    }
+
+    let mut merged = Vec::new();
+    let mut combinations = 1usize;
+    for (i, o) in first.iter().enumerate() {
+        let mut values: Vec<&str> = Vec::new();
+        for job in &jobs {
+            let value = job[i].split_once('=').map(|(_, value)| value);
+            match value {
+                Some(value) if !values.contains(&value) => values.push(value),
+                Some(_) => {}
+                // Deletions like ~key have no value to sweep over.
+                None if job[i] == *o => {}
+                None => return None,
+            }
+        }
+        combinations = combinations.saturating_mul(values.len().max(1));
+        if values.is_empty() {
+            merged.push(o.clone());
+        } else {
+            merged.push(format!("{}={}", override_key(o), values.join(",")));
+        }
+    }
+    (combinations == jobs.len()).then_some(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(jobs: &[&[&str]]) -> Vec<Vec<String>> {
+        jobs.iter()
+            .map(|job| job.iter().map(|o| o.to_string()).collect())
+            .collect()
+    }
+
+    fn merged(merged: &[&str]) -> Option<Vec<String>> {
+        Some(merged.iter().map(|o| o.to_string()).collect())
+    }
+
+    #[test]
+    fn merges_a_full_cross_product() {
+        let overrides = jobs(&[
+            &["lr=0.1", "model=b"],
+            &["lr=0.01", "model=a"],
+            &["lr=0.1", "model=a"],
+            &["lr=0.01", "model=b"],
+        ]);
+        assert_eq!(
+            merge_overrides(&overrides),
+            merged(&["lr=0.01,0.1", "model=a,b"])
+        );
+    }
+
+    #[test]
+    fn does_not_merge_a_partial_product() {
+        // lr=0.1 model=b did not fail, a merged sweep would rerun it.
+        let overrides = jobs(&[
+            &["lr=0.01", "model=a"],
+            &["lr=0.01", "model=b"],
+            &["lr=0.1", "model=a"],
+        ]);
+        assert_eq!(merge_overrides(&overrides), None);
+    }
+
+    #[test]
+    fn does_not_merge_different_keys() {
+        let overrides = jobs(&[&["lr=0.1"], &["lr=0.1", "seed=1"]]);
+        assert_eq!(merge_overrides(&overrides), None);
+        let overrides = jobs(&[&["lr=0.1"], &["seed=1"]]);
+        assert_eq!(merge_overrides(&overrides), None);
+    }
+
+    #[test]
+    fn duplicate_jobs_are_merged_once() {
+        let overrides = jobs(&[&["lr=0.1"], &["lr=0.01"], &["lr=0.1"]]);
+        assert_eq!(merge_overrides(&overrides), merged(&["lr=0.01,0.1"]));
+    }
+
+    #[test]
+    fn deletions_are_kept_as_they_are() {
+        let overrides = jobs(&[&["~seed", "lr=0.1"], &["~seed", "lr=0.01"]]);
+        assert_eq!(
+            merge_overrides(&overrides),
+            merged(&["~seed", "lr=0.01,0.1"])
+        );
+        // Deleting a key in one job and setting it in another is no sweep.
+        let overrides = jobs(&[&["~seed", "lr=0.1"], &["seed=1", "lr=0.01"]]);
+        assert_eq!(merge_overrides(&overrides), None);
+    }
+
+    #[test]
+    fn quoted_and_list_values_stay_whole() {
+        let overrides = jobs(&[
+            &["name='x, y'", "+tags=[a,b]"],
+            &["name='x, y'", "+tags=[c]"],
+        ]);
+        assert_eq!(
+            merge_overrides(&overrides),
+            merged(&["name='x, y'", "+tags=[a,b],[c]"])
+        );
+    }
+
+    #[test]
+    fn quotes_for_the_shell() {
+        assert_eq!(shell_quote("lr=0.1,0.01"), "lr=0.1,0.01");
+        assert_eq!(shell_quote("'a b'"), "\"'a b'\"");
+        assert_eq!(shell_quote("model.name='a b'"), "\"model.name='a b'\"");
+        assert_eq!(shell_quote("[a,b]"), "'[a,b]'");
+        assert_eq!(shell_quote("it's $HOME"), r"'it'\''s $HOME'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn numbers_the_sweep_dir() {
+        let mut rerun = Rerun {
+            cwd: None,
+            script: "train.py".to_string(),
+            overrides: vec!["hydra.sweep.dir=out".to_string(), "lr=0.1".to_string()],
+            sweep_dir: "out".to_string(),
+        };
+        rerun.number_sweep_dir(2);
+        assert_eq!(
+            rerun.shell_command("python"),
+            "python train.py -m lr=0.1 hydra.sweep.dir=out_2"
+        );
+    }
+}
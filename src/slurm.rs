@@ -0,0 +1,441 @@
+use crate::jobid::JobId;
+use crate::table::format_size;
+use crate::{MissingSlurmToolSnafu, PResult};
+use clap::ValueEnum;
+use snafu::ResultExt;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::process::Command as ProcCommand;
+
+// Number of job ids passed to a single sacct call, to keep the command line
+// at a sane length for large sweeps.
+const SACCT_CHUNK_SIZE: usize = 200;
+
+// All Slurm tools are looked up on the PATH, so they can be replaced by stub
+// scripts. Returns None if the tool ran but failed, e.g. because it does not
+// know the requested job ids (anymore).
+fn run_slurm_tool(tool: &str, args: &[&str]) -> PResult<Option<String>> {
+    let output = ProcCommand::new(tool)
+        .args(args)
+        .output()
+        .context(MissingSlurmToolSnafu { tool })?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(String::from_utf8_lossy(&output.stdout).to_string()))
+}
+
+fn parse_job_ids(stdout: &str) -> Vec<JobId> {
+    stdout
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect()
+}
+
+pub fn get_active_slurm_jobs() -> PResult<Vec<JobId>> {
+    let stdout = run_slurm_tool("squeue", &["-h", "-o", "%i", "--me", "-t", "RUNNING"])?;
+    Ok(stdout.as_deref().map(parse_job_ids).unwrap_or_default())
+}
+
+// Returns all tasks of the job that are still in the queue. squeue reports
+// jobs in every state (PENDING, RUNNING, COMPLETING, ...) until they have
+// left the queue, so this includes tasks that have not started yet. With -r,
+// pending array tasks are listed one per line instead of as 12345_[4-7].
+pub fn get_queued_tasks(job: u64) -> PResult<Vec<JobId>> {
+    let stdout = run_slurm_tool("squeue", &["-h", "-r", "-o", "%i", "-j", &job.to_string()])?;
+    Ok(stdout.as_deref().map(parse_job_ids).unwrap_or_default())
+}
+
+// Jobs are given like on the command line, e.g. 12345 for a whole array or
+// 12345_[0-7] for some of its tasks. Returns false if scancel failed, e.g.
+// because some of the jobs do not exist (anymore).
+pub fn cancel_jobs(jobs: &[String]) -> PResult<bool> {
+    let args: Vec<&str> = jobs.iter().map(String::as_str).collect();
+    Ok(run_slurm_tool("scancel", &args)?.is_some())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+#[value(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JobState {
+    Pending,
+    Running,
+    Completing,
+    Completed,
+    Failed,
+    Timeout,
+    OutOfMemory,
+    Cancelled,
+    NodeFail,
+    Preempted,
+    Unknown,
+}
+
+impl JobState {
+    // sacct appends details to some states, e.g. "CANCELLED by 1234".
+    fn parse(s: &str) -> Self {
+        let state = s.split_whitespace().next().unwrap_or("");
+        match state.trim_end_matches('+') {
+            "PENDING" => JobState::Pending,
+            "RUNNING" => JobState::Running,
+            "COMPLETING" => JobState::Completing,
+            "COMPLETED" => JobState::Completed,
+            "FAILED" => JobState::Failed,
+            "TIMEOUT" => JobState::Timeout,
+            "OUT_OF_MEMORY" => JobState::OutOfMemory,
+            "CANCELLED" => JobState::Cancelled,
+            "NODE_FAIL" => JobState::NodeFail,
+            "PREEMPTED" => JobState::Preempted,
+            _ => JobState::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for JobState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub state: JobState,
+    pub exit_code: Option<String>,
+    pub elapsed: Option<String>,
+    pub node: Option<String>,
+    // Maximum resident set size over all steps of the job, in bytes.
+    pub max_rss: Option<u64>,
+}
+
+impl JobInfo {
+    fn new(state: JobState) -> Self {
+        JobInfo {
+            state,
+            exit_code: None,
+            elapsed: None,
+            node: None,
+            max_rss: None,
+        }
+    }
+
+    // One line summary like "COMPLETED, exit 0:0, 00:12:03 on node17, max RSS 1.2G".
+    pub fn summary(&self) -> String {
+        let mut summary = self.state.to_string();
+        if let Some(exit_code) = &self.exit_code {
+            summary.push_str(&format!(", exit {exit_code}"));
+        }
+        if let Some(elapsed) = &self.elapsed {
+            summary.push_str(&format!(", {elapsed}"));
+        }
+        if let Some(node) = &self.node {
+            summary.push_str(&format!(" on {node}"));
+        }
+        if let Some(max_rss) = self.max_rss {
+            summary.push_str(&format!(", max RSS {}", format_size(max_rss)));
+        }
+        summary
+    }
+}
+
+fn non_empty(field: &str) -> Option<String> {
+    let field = field.trim();
+    (!field.is_empty() && field != "None assigned").then(|| field.to_string())
+}
+
+// Memory like 1234K, 12.5M or submitit's 32GB. A plain number is in
+// default_unit bytes, sacct reports bytes but sbatch takes megabytes.
+pub fn parse_memory(s: &str, default_unit: u64) -> Option<u64> {
+    let s = s.trim().trim_end_matches(['B', 'b']);
+    let (number, factor) = match s.chars().last()?.to_ascii_uppercase() {
+        'K' => (&s[..s.len() - 1], 1u64 << 10),
+        'M' => (&s[..s.len() - 1], 1 << 20),
+        'G' => (&s[..s.len() - 1], 1 << 30),
+        'T' => (&s[..s.len() - 1], 1 << 40),
+        _ => (s, default_unit),
+    };
+    Some((number.parse::<f64>().ok()? * factor as f64) as u64)
+}
+
+// Time limits are given as minutes, minutes:seconds, hours:minutes:seconds,
+// days-hours, days-hours:minutes or days-hours:minutes:seconds. Returns
+// seconds, None for unlimited or invalid limits.
+pub fn parse_time_limit(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (days, rest) = match s.split_once('-') {
+        Some((days, rest)) => (Some(days.parse::<u64>().ok()?), rest),
+        None => (None, s),
+    };
+    let parts: Vec<u64> = rest
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let seconds = match (days, &parts[..]) {
+        (None, &[minutes]) => minutes * 60,
+        (None, &[minutes, seconds]) => minutes * 60 + seconds,
+        (_, &[hours, minutes, seconds]) => (hours * 60 + minutes) * 60 + seconds,
+        (Some(_), &[hours]) => hours * 3600,
+        (Some(_), &[hours, minutes]) => (hours * 60 + minutes) * 60,
+        _ => return None,
+    };
+    Some(days.unwrap_or(0) * 86400 + seconds)
+}
+
+// Formats seconds like sacct's Elapsed column, [days-]hours:minutes:seconds.
+pub fn format_duration(seconds: u64) -> String {
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    match days {
+        0 => time,
+        days => format!("{days}-{time}"),
+    }
+}
+
+// Parses `sacct -P -o JobID,State,ExitCode,Elapsed,NodeList,MaxRSS` output.
+// The allocation line of a job carries its state, the step lines (e.g.
+// 12345_3.batch) the memory usage.
+fn parse_sacct(stdout: &str) -> HashMap<JobId, JobInfo> {
+    let mut infos: HashMap<JobId, JobInfo> = HashMap::new();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split('|').collect();
+        let [job_id, state, exit_code, elapsed, node, max_rss, ..] = fields[..] else {
+            continue;
+        };
+        let (job_id, step) = match job_id.split_once('.') {
+            Some((job_id, step)) => (job_id, Some(step)),
+            None => (job_id, None),
+        };
+        let Ok(id) = job_id.parse::<JobId>() else {
+            continue;
+        };
+        let info = infos
+            .entry(id)
+            .or_insert_with(|| JobInfo::new(JobState::Unknown));
+        if step.is_none() {
+            info.state = JobState::parse(state);
+            info.exit_code = non_empty(exit_code);
+            info.elapsed = non_empty(elapsed);
+            info.node = non_empty(node);
+        }
+        if let Some(rss) = parse_memory(max_rss, 1) {
+            info.max_rss = Some(info.max_rss.map_or(rss, |max| max.max(rss)));
+        }
+    }
+    infos
+}
+
+// Runs sacct for the given jobs in chunks and concatenates the output.
+fn run_sacct(jobs: &BTreeSet<u64>, fields: &str) -> PResult<Option<String>> {
+    let jobs: Vec<String> = jobs.iter().map(|job| job.to_string()).collect();
+    let mut output = String::new();
+    for chunk in jobs.chunks(SACCT_CHUNK_SIZE) {
+        let stdout = run_slurm_tool("sacct", &["-n", "-P", "-o", fields, "-j", &chunk.join(",")])?;
+        let Some(stdout) = stdout else {
+            return Ok(None);
+        };
+        output.push_str(&stdout);
+    }
+    Ok(Some(output))
+}
+
+fn get_sacct_infos(jobs: &BTreeSet<u64>) -> PResult<Option<HashMap<JobId, JobInfo>>> {
+    let stdout = run_sacct(jobs, "JobID,State,ExitCode,Elapsed,NodeList,MaxRSS")?;
+    Ok(stdout.as_deref().map(parse_sacct))
+}
+
+fn get_squeue_infos(jobs: &BTreeSet<u64>) -> PResult<HashMap<JobId, JobInfo>> {
+    let jobs: Vec<String> = jobs.iter().map(|job| job.to_string()).collect();
+    let stdout = run_slurm_tool(
+        "squeue",
+        &["-h", "-r", "-o", "%i|%T|%M|%N", "-j", &jobs.join(",")],
+    )?;
+    Ok(stdout
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('|').collect();
+            let [job_id, state, elapsed, node, ..] = fields[..] else {
+                return None;
+            };
+            let mut info = JobInfo::new(JobState::parse(state));
+            info.elapsed = non_empty(elapsed);
+            info.node = non_empty(node);
+            Some((job_id.trim().parse().ok()?, info))
+        })
+        .collect())
+}
+
+// Returns what Slurm knows about the given jobs. sacct knows about finished
+// jobs too, but needs job accounting; if it is not available we fall back to
+// squeue, which only knows about queued jobs. Jobs Slurm does not know about
+// are missing from the map.
+pub fn get_job_infos<'a>(
+    ids: impl IntoIterator<Item = &'a JobId>,
+) -> PResult<HashMap<JobId, JobInfo>> {
+    let jobs: BTreeSet<u64> = ids.into_iter().map(|id| id.job).collect();
+    if jobs.is_empty() {
+        return Ok(HashMap::new());
+    }
+    match get_sacct_infos(&jobs) {
+        Ok(Some(infos)) => Ok(infos),
+        Ok(None) | Err(_) => get_squeue_infos(&jobs),
+    }
+}
+
+// What a job actually used according to sacct. Times are in seconds.
+#[derive(Debug, Clone)]
+pub struct JobUsage {
+    pub state: JobState,
+    pub elapsed: Option<u64>,
+    // Summed over all cpus, so up to elapsed * cpus.
+    pub cpu_time: Option<u64>,
+    pub cpus: Option<u64>,
+    pub gpus: Option<u64>,
+    pub max_rss: Option<u64>,
+}
+
+// sacct's TotalCPU looks like [days-][hours:]minutes:seconds[.fraction].
+fn parse_cpu_time(s: &str) -> Option<u64> {
+    let s = s.trim().split('.').next()?;
+    let (days, rest) = match s.split_once('-') {
+        Some((days, rest)) => (days.parse::<u64>().ok()?, rest),
+        None => (0, s),
+    };
+    let parts: Vec<u64> = rest
+        .split(':')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    let seconds = match parts[..] {
+        [minutes, seconds] => minutes * 60 + seconds,
+        [hours, minutes, seconds] => (hours * 60 + minutes) * 60 + seconds,
+        _ => return None,
+    };
+    Some(days * 86400 + seconds)
+}
+
+// Allocated trackable resources look like cpu=10,mem=32G,node=1,gres/gpu=2.
+fn parse_tres_gpus(s: &str) -> Option<u64> {
+    s.split(',')
+        .find_map(|tres| tres.strip_prefix("gres/gpu="))
+        .and_then(|gpus| gpus.parse().ok())
+}
+
+// Parses `sacct -P -o JobID,State,ElapsedRaw,TotalCPU,AllocCPUS,AllocTRES,MaxRSS`
+// output. As in parse_sacct, the memory usage comes from the step lines.
+fn parse_sacct_usage(stdout: &str) -> HashMap<JobId, JobUsage> {
+    let mut usages: HashMap<JobId, JobUsage> = HashMap::new();
+    for line in stdout.lines() {
+        let fields: Vec<&str> = line.split('|').collect();
+        let [job_id, state, elapsed, cpu_time, cpus, tres, max_rss, ..] = fields[..] else {
+            continue;
+        };
+        let (job_id, step) = match job_id.split_once('.') {
+            Some((job_id, step)) => (job_id, Some(step)),
+            None => (job_id, None),
+        };
+        let Ok(id) = job_id.parse::<JobId>() else {
+            continue;
+        };
+        let usage = usages.entry(id).or_insert_with(|| JobUsage {
+            state: JobState::Unknown,
+            elapsed: None,
+            cpu_time: None,
+            cpus: None,
+            gpus: None,
+            max_rss: None,
+        });
+        if step.is_none() {
+            usage.state = JobState::parse(state);
+            usage.elapsed = elapsed.trim().parse().ok();
+            usage.cpu_time = parse_cpu_time(cpu_time);
+            usage.cpus = cpus.trim().parse().ok();
+            usage.gpus = parse_tres_gpus(tres);
+        }
+        if let Some(rss) = parse_memory(max_rss, 1) {
+            usage.max_rss = Some(usage.max_rss.map_or(rss, |max| max.max(rss)));
+        }
+    }
+    usages
+}
+
+// Unlike get_job_infos there is no fallback to squeue, which does not know
+// about used resources. Jobs sacct does not know about are missing.
+pub fn get_job_usage<'a>(
+    ids: impl IntoIterator<Item = &'a JobId>,
+) -> PResult<HashMap<JobId, JobUsage>> {
+    let jobs: BTreeSet<u64> = ids.into_iter().map(|id| id.job).collect();
+    if jobs.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let stdout = run_sacct(
+        &jobs,
+        "JobID,State,ElapsedRaw,TotalCPU,AllocCPUS,AllocTRES,MaxRSS",
+    )?;
+    Ok(stdout.as_deref().map(parse_sacct_usage).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_memory_with_and_without_units() {
+        assert_eq!(parse_memory("1234K", 1), Some(1234 << 10));
+        assert_eq!(parse_memory("12.5M", 1), Some(25 << 19));
+        assert_eq!(parse_memory("2048", 1), Some(2048));
+        assert_eq!(parse_memory("32GB", 1 << 20), Some(32 << 30));
+        assert_eq!(parse_memory("2000", 1 << 20), Some(2000 << 20));
+        assert_eq!(parse_memory("1t", 1), Some(1 << 40));
+        assert_eq!(parse_memory("", 1), None);
+        assert_eq!(parse_memory("lots", 1), None);
+    }
+
+    #[test]
+    fn parses_all_time_limit_forms() {
+        assert_eq!(parse_time_limit("60"), Some(3600));
+        assert_eq!(parse_time_limit("30:15"), Some(1815));
+        assert_eq!(parse_time_limit("1:00:00"), Some(3600));
+        assert_eq!(parse_time_limit("2-12"), Some(2 * 86400 + 12 * 3600));
+        assert_eq!(parse_time_limit("1-00:00"), Some(86400));
+        assert_eq!(parse_time_limit("1-02:03:04"), Some(93784));
+        assert_eq!(parse_time_limit("UNLIMITED"), None);
+        assert_eq!(parse_time_limit("1:2:3:4"), None);
+    }
+
+    #[test]
+    fn parses_cpu_time_with_fractions() {
+        assert_eq!(parse_cpu_time("01:02.500"), Some(62));
+        assert_eq!(parse_cpu_time("1:00:00"), Some(3600));
+        assert_eq!(parse_cpu_time("1-00:00:01"), Some(86401));
+        assert_eq!(parse_cpu_time("5"), None);
+    }
+
+    #[test]
+    fn usage_takes_max_rss_from_the_steps() {
+        let stdout = "\
+1000_0|COMPLETED|3600|02:00:00|4|billing=4,cpu=4,gres/gpu=2,mem=16G,node=1|
+1000_0.batch|COMPLETED|3600|01:00:00|4|cpu=4,mem=16G,node=1|2.5G
+1000_0.extern|COMPLETED|3600|00:00:00|4|cpu=4,mem=16G,node=1|1024K
+1000_1|RUNNING|60|00:30|4|cpu=4,node=1|
+";
+        let usages = parse_sacct_usage(stdout);
+        let usage = &usages[&"1000_0".parse().unwrap()];
+        assert_eq!(usage.state, JobState::Completed);
+        assert_eq!(usage.elapsed, Some(3600));
+        assert_eq!(usage.cpu_time, Some(7200));
+        assert_eq!(usage.cpus, Some(4));
+        assert_eq!(usage.gpus, Some(2));
+        assert_eq!(usage.max_rss, Some(5 << 29));
+        let usage = &usages[&"1000_1".parse().unwrap()];
+        assert_eq!(usage.state, JobState::Running);
+        assert_eq!(usage.gpus, None);
+        assert_eq!(usage.max_rss, None);
+    }
+}
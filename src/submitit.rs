@@ -0,0 +1,475 @@
+use crate::pickle::{self, PickleValue};
+use crate::slurm;
+use crate::{FileNotFoundSnafu, InvalidPickleSnafu, PResult, SubmissionScriptNotFoundSnafu};
+use snafu::ResultExt;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+// The tag is the first thing in a result pickle.
+const STATUS_PREFIX_BYTES: u64 = 4096;
+// Jobs may return whole models, which are not worth decoding.
+const MAX_RESULT_BYTES: u64 = 64 * 1024 * 1024;
+
+// Submitit names the files next to the logs after the job, e.g.
+//   .submitit/<job>/<job>_result.pkl
+fn job_file(job_dir: &Path, suffix: &str) -> Option<PathBuf> {
+    let job = job_dir.file_name()?.to_str()?;
+    Some(job_dir.join(format!("{job}_{suffix}")))
+}
+
+// What the submitted function returned or raised, as a pickled
+// ("success", value) or ("error", traceback) tuple.
+fn result_pickle_path(job_dir: &Path) -> Option<PathBuf> {
+    job_file(job_dir, "result.pkl")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultStatus {
+    Success,
+    Error,
+}
+
+impl fmt::Display for ResultStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResultStatus::Success => write!(f, "success"),
+            ResultStatus::Error => write!(f, "error"),
+        }
+    }
+}
+
+fn parse_status(tag: &str) -> Option<ResultStatus> {
+    match tag {
+        "success" => Some(ResultStatus::Success),
+        "error" => Some(ResultStatus::Error),
+        _ => None,
+    }
+}
+
+// Only reads the start of the pickle, so it is cheap even for large results.
+pub fn result_status(job_dir: &Path) -> Option<ResultStatus> {
+    let file = File::open(result_pickle_path(job_dir)?).ok()?;
+    let mut prefix = Vec::new();
+    file.take(STATUS_PREFIX_BYTES)
+        .read_to_end(&mut prefix)
+        .ok()?;
+    parse_status(&pickle::first_string(&prefix)?)
+}
+
+pub enum JobResult {
+    Success(PickleValue),
+    // The returned value was not decoded, because it is too large.
+    LargeSuccess(u64),
+    Error(String),
+}
+
+// Returns None if the job has not finished (yet) or was killed before
+// submitit could write its result.
+pub fn read_result(job_dir: &Path) -> PResult<Option<JobResult>> {
+    let Some(path) = result_pickle_path(job_dir).filter(|path| path.is_file()) else {
+        return Ok(None);
+    };
+    let len = fs::metadata(&path)
+        .context(FileNotFoundSnafu { path: path.clone() })?
+        .len();
+    if len > MAX_RESULT_BYTES && result_status(job_dir) == Some(ResultStatus::Success) {
+        return Ok(Some(JobResult::LargeSuccess(len)));
+    }
+    let data = fs::read(&path).context(FileNotFoundSnafu { path: path.clone() })?;
+    let invalid = |reason: &str| {
+        InvalidPickleSnafu {
+            path: path.clone(),
+            reason,
+        }
+        .build()
+    };
+    let value = pickle::loads(&data).map_err(|reason| invalid(&reason))?;
+    let PickleValue::Tuple(items) = value else {
+        return Err(invalid("not a tuple"));
+    };
+    match <[PickleValue; 2]>::try_from(items) {
+        Ok([PickleValue::Str(tag), value]) => match (parse_status(&tag), value) {
+            (Some(ResultStatus::Success), value) => Ok(Some(JobResult::Success(value))),
+            (Some(ResultStatus::Error), PickleValue::Str(traceback)) => {
+                Ok(Some(JobResult::Error(traceback)))
+            }
+            (Some(ResultStatus::Error), value) => Ok(Some(JobResult::Error(value.to_string()))),
+            (None, _) => Err(invalid(&format!("unknown tag {tag}"))),
+        },
+        _ => Err(invalid("not a (tag, value) pair")),
+    }
+}
+
+// Something requested per node, task, cpu or gpu, as written in the script.
+#[derive(Debug, Clone)]
+pub struct Amount {
+    pub value: String,
+    pub per: Option<&'static str>,
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.per {
+            Some(per) => write!(f, "{} per {per}", self.value),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+fn amount(value: String, per: Option<&'static str>) -> Option<Amount> {
+    Some(Amount { value, per })
+}
+
+// The resources asked for in the #SBATCH lines of the submission script.
+#[derive(Debug, Clone, Default)]
+pub struct SbatchRequest {
+    pub script: PathBuf,
+    pub job_name: Option<String>,
+    pub partition: Option<String>,
+    pub nodes: Option<String>,
+    pub tasks: Option<Amount>,
+    pub cpus: Option<Amount>,
+    pub gpus: Option<Amount>,
+    pub mem: Option<Amount>,
+    // In seconds, None if unlimited.
+    pub time_limit: Option<u64>,
+    pub array: Option<String>,
+}
+
+// The number at the end, e.g. 2 for a100:2, and the lower bound of ranges
+// like 1-4.
+fn parse_count(s: &str) -> Option<u64> {
+    let last = s.rsplit(':').next()?;
+    last.split('-').next()?.parse().ok()
+}
+
+impl SbatchRequest {
+    fn node_count(&self) -> u64 {
+        self.nodes.as_deref().and_then(parse_count).unwrap_or(1)
+    }
+
+    fn task_count(&self) -> u64 {
+        let Some(tasks) = &self.tasks else {
+            return self.node_count();
+        };
+        let count = parse_count(&tasks.value).unwrap_or(1);
+        match tasks.per {
+            Some(_) => count * self.node_count(),
+            None => count,
+        }
+    }
+
+    fn total(&self, amount: &Amount) -> Option<u64> {
+        let count = parse_count(&amount.value)?;
+        Some(match amount.per {
+            Some("node") => count * self.node_count(),
+            Some("task") => count * self.task_count(),
+            Some("gpu") => count * self.gpu_count().unwrap_or(1),
+            _ => count,
+        })
+    }
+
+    pub fn gpu_count(&self) -> Option<u64> {
+        self.total(self.gpus.as_ref()?)
+    }
+
+    // Memory of the whole job in bytes. Per cpu and per gpu requests are
+    // multiplied with what was allocated, if known.
+    pub fn mem_bytes(&self, cpus: Option<u64>, gpus: Option<u64>) -> Option<u64> {
+        let mem = self.mem.as_ref()?;
+        // Megabytes without a unit. 0 means all memory of the node, which is
+        // unknown here.
+        let bytes = slurm::parse_memory(&mem.value, 1 << 20).filter(|&bytes| bytes > 0)?;
+        let count = match mem.per {
+            Some("node") => self.node_count(),
+            Some("cpu") => cpus.or_else(|| self.total(self.cpus.as_ref()?))?,
+            Some("gpu") => gpus.or_else(|| self.gpu_count())?,
+            _ => 1,
+        };
+        Some(bytes * count)
+    }
+}
+
+fn long_option(short: char) -> String {
+    let long = match short {
+        'J' => "job-name",
+        'p' => "partition",
+        'N' => "nodes",
+        'n' => "ntasks",
+        'c' => "cpus-per-task",
+        'G' => "gpus",
+        't' => "time",
+        'a' => "array",
+        short => return short.to_string(),
+    };
+    long.to_string()
+}
+
+// Options of the #SBATCH lines as (long name, value) pairs, flags have an
+// empty value. Like sbatch, stops at the first command of the script.
+fn sbatch_options(script: &str) -> Vec<(String, String)> {
+    let mut options = Vec::new();
+    for line in script.lines().map(str::trim) {
+        let Some(rest) = line.strip_prefix("#SBATCH") else {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            break;
+        };
+        let mut tokens = rest
+            .split_whitespace()
+            .take_while(|token| !token.starts_with('#'))
+            .peekable();
+        while let Some(token) = tokens.next() {
+            let (name, value) = if let Some(long) = token.strip_prefix("--") {
+                match long.split_once('=') {
+                    Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                    None => (long.to_string(), None),
+                }
+            } else if let Some(short) = token.strip_prefix('-') {
+                let mut chars = short.chars();
+                let Some(option) = chars.next() else {
+                    continue;
+                };
+                let attached = chars.as_str().trim_start_matches('=');
+                (
+                    long_option(option),
+                    (!attached.is_empty()).then(|| attached.to_string()),
+                )
+            } else {
+                continue;
+            };
+            let value = value.or_else(|| {
+                tokens
+                    .next_if(|token| !token.starts_with('-'))
+                    .map(str::to_string)
+            });
+            options.push((name, value.unwrap_or_default()));
+        }
+    }
+    options
+}
+
+// Later options override earlier ones, as with sbatch.
+fn parse_sbatch(script: &str) -> SbatchRequest {
+    let mut request = SbatchRequest::default();
+    for (name, value) in sbatch_options(script) {
+        match name.as_str() {
+            "job-name" => request.job_name = Some(value),
+            "partition" => request.partition = Some(value),
+            "nodes" => request.nodes = Some(value),
+            "ntasks" => request.tasks = amount(value, None),
+            "ntasks-per-node" => request.tasks = amount(value, Some("node")),
+            "cpus-per-task" => request.cpus = amount(value, Some("task")),
+            "cpus-per-gpu" => request.cpus = amount(value, Some("gpu")),
+            "gpus" => request.gpus = amount(value, None),
+            "gpus-per-node" => request.gpus = amount(value, Some("node")),
+            "gpus-per-task" => request.gpus = amount(value, Some("task")),
+            // e.g. gpu:2 or gpu:a100:2, counted per node.
+            "gres" => {
+                if let Some(gpus) = value
+                    .strip_prefix("gpu:")
+                    .or((value == "gpu").then_some("1"))
+                {
+                    request.gpus = amount(gpus.to_string(), Some("node"));
+                }
+            }
+            "mem" => request.mem = amount(value, Some("node")),
+            "mem-per-cpu" => request.mem = amount(value, Some("cpu")),
+            "mem-per-gpu" => request.mem = amount(value, Some("gpu")),
+            "time" => request.time_limit = slurm::parse_time_limit(&value),
+            "array" => request.array = Some(value),
+            _ => {}
+        }
+    }
+    request
+}
+
+// Submitit writes one script per job array, named after the array's job id,
+// into the folder of its tasks. Older versions just call it submission.sh.
+fn submission_script_path(job_dir: &Path) -> Option<PathBuf> {
+    let name = job_dir.file_name()?.to_str()?;
+    let job = name.split('_').next()?;
+    [
+        format!("{name}_submission.sh"),
+        format!("{job}_submission.sh"),
+        "submission.sh".to_string(),
+    ]
+    .into_iter()
+    .map(|file| job_dir.join(file))
+    .find(|path| path.is_file())
+}
+
+pub fn read_sbatch_request(job_dir: &Path) -> PResult<SbatchRequest> {
+    let Some(script) = submission_script_path(job_dir) else {
+        return SubmissionScriptNotFoundSnafu { dir: job_dir }.fail();
+    };
+    let content = fs::read_to_string(&script).context(FileNotFoundSnafu {
+        path: script.clone(),
+    })?;
+    Ok(SbatchRequest {
+        script,
+        ..parse_sbatch(&content)
+    })
+}
+
+// The call submitit runs on the node, from the pickled DelayedSubmission.
+pub struct Submission {
+    pub function: String,
+    pub args: Vec<PickleValue>,
+    pub kwargs: Vec<(PickleValue, PickleValue)>,
+}
+
+// Functions that cannot be imported on the node, e.g. ones defined in the
+// launching script, are pickled by value by cloudpickle, as a call to
+// _make_function with a code object. Their name is in the code object after
+// the file name.
+fn pickled_function_name(args: &[PickleValue]) -> Option<String> {
+    let PickleValue::Object { args: code, .. } = args.first()? else {
+        return None;
+    };
+    let file = code
+        .iter()
+        .position(|arg| matches!(arg, PickleValue::Str(s) if s.ends_with(".py")))?;
+    let PickleValue::Str(name) = code.get(file + 1)? else {
+        return None;
+    };
+    match args.get(1).and_then(|globals| globals.get("__name__")) {
+        Some(PickleValue::Str(module)) => Some(format!("{module}.{name}")),
+        _ => Some(name.clone()),
+    }
+}
+
+fn callable_name(function: &PickleValue) -> String {
+    match function {
+        PickleValue::Global(name) => name.clone(),
+        PickleValue::Object { class, args, .. } if class.ends_with("._make_function") => {
+            pickled_function_name(args).unwrap_or_else(|| "<function>".to_string())
+        }
+        PickleValue::Object { class, args, .. } if class == "functools.partial" => {
+            match args.first() {
+                Some(function) => format!("functools.partial({})", callable_name(function)),
+                None => class.clone(),
+            }
+        }
+        // Callable objects like the Hydra launcher.
+        PickleValue::Object { class, .. } => format!("{class} object"),
+        value => value.to_string(),
+    }
+}
+
+// Returns None for jobs that were not submitted by submitit, or whose folder
+// was cleaned up.
+pub fn read_submission(job_dir: &Path) -> PResult<Option<Submission>> {
+    let Some(path) = job_file(job_dir, "submitted.pkl").filter(|path| path.is_file()) else {
+        return Ok(None);
+    };
+    let data = fs::read(&path).context(FileNotFoundSnafu { path: path.clone() })?;
+    let invalid = |reason: &str| {
+        InvalidPickleSnafu {
+            path: path.clone(),
+            reason,
+        }
+        .build()
+    };
+    let value = pickle::loads(&data).map_err(|reason| invalid(&reason))?;
+    let Some(state) = (match &value {
+        PickleValue::Object { state, .. } => state.as_deref(),
+        _ => None,
+    }) else {
+        return Err(invalid("not a submitit DelayedSubmission"));
+    };
+    let function = state
+        .get("function")
+        .ok_or_else(|| invalid("no submitted function"))?;
+    let args = match state.get("args") {
+        Some(PickleValue::Tuple(args) | PickleValue::List(args)) => args.clone(),
+        _ => Vec::new(),
+    };
+    let kwargs = match state.get("kwargs") {
+        Some(PickleValue::Dict(kwargs)) => kwargs.clone(),
+        _ => Vec::new(),
+    };
+    Ok(Some(Submission {
+        function: callable_name(function),
+        args,
+        kwargs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "\
+#!/bin/bash
+
+# Parameters
+#SBATCH --cpus-per-task=8
+#SBATCH -J train -p gpu
+#SBATCH -N2 -c=4
+#SBATCH --exclusive
+#SBATCH --time 60  # one hour
+#SBATCH --gres=gpu:a100:2
+#SBATCH --mem=32GB
+#SBATCH -t 1-00:00
+
+srun python train.py
+#SBATCH --partition=ignored
+";
+
+    #[test]
+    fn reads_short_long_and_attached_options() {
+        let options = sbatch_options(SCRIPT);
+        let options: Vec<(&str, &str)> = options
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(
+            options,
+            [
+                ("cpus-per-task", "8"),
+                ("job-name", "train"),
+                ("partition", "gpu"),
+                ("nodes", "2"),
+                ("cpus-per-task", "4"),
+                ("exclusive", ""),
+                ("time", "60"),
+                ("gres", "gpu:a100:2"),
+                ("mem", "32GB"),
+                ("time", "1-00:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn later_options_win_and_totals_are_per_node() {
+        let request = parse_sbatch(SCRIPT);
+        assert_eq!(request.job_name.as_deref(), Some("train"));
+        assert_eq!(request.partition.as_deref(), Some("gpu"));
+        assert_eq!(request.time_limit, Some(86400));
+        // gpu:a100:2 on each of the two nodes.
+        assert_eq!(request.gpu_count(), Some(4));
+        assert_eq!(request.mem_bytes(None, None), Some(2 * (32 << 30)));
+    }
+
+    #[test]
+    fn memory_per_cpu_uses_the_allocation() {
+        let request = parse_sbatch("#SBATCH --mem-per-cpu=2000\n#SBATCH -c 4\n");
+        assert_eq!(request.mem_bytes(None, None), Some(4 * (2000 << 20)));
+        assert_eq!(request.mem_bytes(Some(8), None), Some(8 * (2000 << 20)));
+        // All memory of the node.
+        let request = parse_sbatch("#SBATCH --mem=0\n");
+        assert_eq!(request.mem_bytes(None, None), None);
+    }
+
+    #[test]
+    fn bare_gres_is_one_gpu_per_node() {
+        let request = parse_sbatch("#SBATCH --gres=gpu --nodes=3\n");
+        assert_eq!(request.gpu_count(), Some(3));
+        let request = parse_sbatch("#SBATCH --gres=shard:2\n");
+        assert_eq!(request.gpu_count(), None);
+    }
+}
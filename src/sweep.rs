@@ -0,0 +1,115 @@
+use crate::hydra;
+use crate::slurm::{JobInfo, JobState};
+use std::convert::Infallible;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+// Submitit logs these to stderr when the wrapped function returns or raises.
+const SUCCESS_MARKER: &str = "Job completed successfully";
+const FAILURE_MARKERS: [&str; 2] = ["has failed", "Traceback (most recent call last)"];
+
+// Hydra sweeper functions that expand into several values.
+const SWEEP_FUNCTIONS: [&str; 6] = [
+    "range(",
+    "choice(",
+    "interval(",
+    "glob(",
+    "shuffle(",
+    "sort(",
+];
+
+// A sweep (one multirun launch) picked on the command line, either the most
+// recent one or by its YYYY-MM-DD/hh-mm-ss directory. A plain date picks all
+// sweeps of that day.
+#[derive(Debug, Clone)]
+pub enum SweepSelector {
+    Latest,
+    Name(String),
+}
+
+impl FromStr for SweepSelector {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "latest" => SweepSelector::Latest,
+            name => SweepSelector::Name(name.trim_end_matches('/').to_string()),
+        })
+    }
+}
+
+pub fn sweep_name_matches(selected: &str, name: &str) -> bool {
+    name == selected
+        || name
+            .strip_prefix(selected)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+// Whether an override value expands into several jobs, e.g. 0.1,0.01 or
+// range(1,5). Commas inside brackets or quotes belong to a single value.
+fn is_sweep_value(value: &str) -> bool {
+    if SWEEP_FUNCTIONS.iter().any(|f| value.starts_with(f)) {
+        return true;
+    }
+    let mut depth = 0i32;
+    let mut quote = None;
+    for c in value.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '{' | '(') => depth += 1,
+            (None, ']' | '}' | ')') => depth -= 1,
+            (None, ',') if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+// Keys of the overrides that are swept over in the given sweep directory.
+pub fn varying_keys(sweep_dir: &Path) -> Vec<String> {
+    let Ok(multirun_yaml) = fs::read_to_string(sweep_dir.join("multirun.yaml")) else {
+        return Vec::new();
+    };
+    hydra::overrides_list(&multirun_yaml, "task")
+        .iter()
+        .filter_map(|o| o.split_once('='))
+        .filter(|(_, value)| is_sweep_value(value))
+        .map(|(key, _)| key.trim_start_matches('+').to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Done,
+    Failed,
+    Running,
+    Pending,
+    Unknown,
+}
+
+// Slurm has the final word on the state of a job. Once it has forgotten about
+// the job, the end of the stderr log tells us how it went.
+pub fn outcome(info: Option<&JobInfo>, err_tail: Option<&str>) -> Outcome {
+    match info.map(|info| info.state) {
+        Some(JobState::Pending) => return Outcome::Pending,
+        Some(JobState::Running | JobState::Completing) => return Outcome::Running,
+        Some(JobState::Completed) => return Outcome::Done,
+        Some(
+            JobState::Failed
+            | JobState::Timeout
+            | JobState::OutOfMemory
+            | JobState::Cancelled
+            | JobState::NodeFail
+            | JobState::Preempted,
+        ) => return Outcome::Failed,
+        Some(JobState::Unknown) | None => {}
+    }
+    match err_tail {
+        Some(tail) if tail.contains(SUCCESS_MARKER) => Outcome::Done,
+        Some(tail) if FAILURE_MARKERS.iter().any(|m| tail.contains(m)) => Outcome::Failed,
+        _ => Outcome::Unknown,
+    }
+}
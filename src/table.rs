@@ -0,0 +1,62 @@
+use colored::Colorize;
+use std::io::{self, Write};
+
+// Prints rows as left-aligned columns separated by two spaces, with a bold
+// header line. Cells must not contain ANSI escapes, as they would throw off
+// the width computation.
+pub fn print_table(out: &mut dyn Write, headers: &[&str], rows: &[Vec<String>]) -> io::Result<()> {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let header = format_row(headers.iter().map(|h| h.to_string()), &widths);
+    writeln!(out, "{}", header.bold())?;
+    for row in rows {
+        writeln!(out, "{}", format_row(row.iter().cloned(), &widths))?;
+    }
+    Ok(())
+}
+
+fn format_row(cells: impl Iterator<Item = String>, widths: &[usize]) -> String {
+    cells
+        .zip(widths)
+        .map(|(cell, width)| format!("{cell:<width$}"))
+        .collect::<Vec<_>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+// Human readable file size, e.g. 1.5K or 12.0M.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{bytes}B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1}{}", UNITS[unit])
+}
+
+// Joins cells into a CSV line, quoting cells that contain separators, quotes
+// or line breaks.
+pub fn csv_row(cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,440 @@
+use crate::jobid::JobId;
+use crate::slurm::{JobInfo, JobState};
+use crate::{
+    get_job_infos, get_log_pathbuf, pager_command, InvalidRegexSnafu, Job, LogFollower, PResult,
+    TerminalSnafu, Workspace, FOLLOW_POLL_INTERVAL,
+};
+use base64::Engine;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use regex::Regex;
+use snafu::ResultExt;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+const STREAMS: [&str; 2] = ["out", "err"];
+
+// Only the end of a log is kept, so that moving through the jobs stays fast
+// with huge logs. The pager still shows all of it.
+const MAX_LINES: usize = 10_000;
+const MAX_TAIL_BYTES: u64 = 8 * 1024 * 1024;
+
+const HELP: &str = "q quit  ↑↓ job  tab out/err  PgUp/PgDn/g/G scroll  / search  n/N next/prev  f follow  y copy path  o pager  r refresh";
+
+enum Mode {
+    Normal,
+    // The search pattern being typed.
+    Search(String),
+}
+
+struct App<'a> {
+    ws: &'a Workspace,
+    // Newest jobs first, like in search.
+    jobs: Vec<(JobId, Job)>,
+    infos: HashMap<JobId, JobInfo>,
+    list_state: ListState,
+    // Index into STREAMS.
+    stream: usize,
+    follower: Option<LogFollower>,
+    lines: Vec<String>,
+    // First line of the log shown and the number of lines that fit.
+    scroll: usize,
+    height: usize,
+    follow: bool,
+    search: Option<Regex>,
+    current_match: Option<usize>,
+    mode: Mode,
+    status: String,
+}
+
+// Carriage returns (progress bars) and tabs would mess up the layout. Only
+// the last state of a progress bar is kept.
+fn sanitize(line: String) -> String {
+    let line = match line.trim_end_matches('\r').rsplit_once('\r') {
+        Some((_, last)) => last.to_string(),
+        None => line,
+    };
+    line.replace('\t', "    ")
+}
+
+fn state_style(state: JobState) -> Style {
+    match state {
+        JobState::Running | JobState::Completing => Style::new().fg(Color::Green),
+        JobState::Pending => Style::new().fg(Color::Yellow),
+        JobState::Completed => Style::new(),
+        JobState::Unknown => Style::new().fg(Color::DarkGray),
+        _ => Style::new().fg(Color::Red),
+    }
+}
+
+// Splits a line into spans with the matches of the search highlighted.
+fn highlight<'l>(line: &'l str, search: Option<&Regex>) -> Line<'l> {
+    let Some(search) = search else {
+        return Line::raw(line);
+    };
+    let mut spans = Vec::new();
+    let mut last = 0;
+    for m in search.find_iter(line) {
+        spans.push(Span::raw(&line[last..m.start()]));
+        spans.push(Span::raw(m.as_str()).black().on_yellow());
+        last = m.end();
+    }
+    spans.push(Span::raw(&line[last..]));
+    Line::from(spans)
+}
+
+// Copies to the clipboard of the terminal emulator with an OSC 52 escape
+// sequence, which also works over SSH.
+fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{encoded}\x07")?;
+    stdout.flush()
+}
+
+impl<'a> App<'a> {
+    fn new(ws: &'a Workspace) -> PResult<Self> {
+        let mut app = App {
+            ws,
+            jobs: Vec::new(),
+            infos: HashMap::new(),
+            list_state: ListState::default(),
+            stream: 0,
+            follower: None,
+            lines: Vec::new(),
+            scroll: 0,
+            height: 0,
+            follow: false,
+            search: None,
+            current_match: None,
+            mode: Mode::Normal,
+            status: String::new(),
+        };
+        app.load_jobs()?;
+        Ok(app)
+    }
+
+    // (Re)reads the job list and the Slurm states, keeping the selected job.
+    fn load_jobs(&mut self) -> PResult<()> {
+        let selected = self.selected().map(|(id, _)| *id);
+        self.jobs = self.ws.build_job_map()?.into_iter().rev().collect();
+        self.infos = get_job_infos(self.jobs.iter().map(|(id, _)| id), false)?;
+        let index = selected
+            .and_then(|selected| self.jobs.iter().position(|(id, _)| *id == selected))
+            .unwrap_or(0);
+        self.list_state
+            .select((!self.jobs.is_empty()).then_some(index));
+        self.open_log();
+        Ok(())
+    }
+
+    fn selected(&self) -> Option<&(JobId, Job)> {
+        self.jobs.get(self.list_state.selected()?)
+    }
+
+    fn open_log(&mut self) {
+        self.follower = self
+            .selected()
+            .map(|(_, job)| LogFollower::new(None, job.dir.clone(), STREAMS[self.stream]));
+        self.lines.clear();
+        self.scroll = 0;
+        self.current_match = None;
+        if let Some(follower) = &mut self.follower {
+            if follower.skip_to_tail(MAX_LINES, MAX_TAIL_BYTES) {
+                self.status = "Showing the end of the log, o opens all of it.".to_string();
+            }
+        }
+        self.poll_log();
+    }
+
+    fn poll_log(&mut self) {
+        if let Some(follower) = &mut self.follower {
+            self.lines.extend(follower.poll().into_iter().map(sanitize));
+        }
+        let dropped = self.lines.len().saturating_sub(MAX_LINES);
+        if dropped > 0 {
+            self.lines.drain(..dropped);
+            self.scroll = self.scroll.saturating_sub(dropped);
+            self.current_match = self
+                .current_match
+                .and_then(|line| line.checked_sub(dropped));
+        }
+        if self.follow {
+            self.scroll = self.max_scroll();
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(self.max_scroll());
+        // Like less +F, scrolling up stops following.
+        if delta < 0 {
+            self.follow = false;
+        }
+    }
+
+    fn select_job(&mut self, delta: isize) {
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        let index = selected
+            .saturating_add_signed(delta)
+            .min(self.jobs.len() - 1);
+        if index != selected {
+            self.list_state.select(Some(index));
+            self.open_log();
+        }
+    }
+
+    fn find_match(&mut self, forward: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let from = self.current_match.unwrap_or(self.scroll);
+        let found = if forward {
+            let start = if self.current_match.is_some() {
+                from + 1
+            } else {
+                from
+            };
+            (start..self.lines.len()).find(|&i| search.is_match(&self.lines[i]))
+        } else {
+            (0..from).rev().find(|&i| search.is_match(&self.lines[i]))
+        };
+        match found {
+            Some(line) => {
+                self.current_match = Some(line);
+                self.scroll = line.saturating_sub(self.height / 2).min(self.max_scroll());
+                self.follow = false;
+            }
+            None => self.status = format!("No more matches for {}.", search.as_str()),
+        }
+    }
+
+    fn log_path(&self) -> Option<PathBuf> {
+        let (_, job) = self.selected()?;
+        let log_fp = get_log_pathbuf(&job.dir, STREAMS[self.stream]).ok()?;
+        Some(std::path::absolute(&log_fp).unwrap_or(log_fp))
+    }
+
+    // Hands the terminal over to the pager until it exits.
+    fn open_in_pager(&mut self, terminal: &mut DefaultTerminal) -> PResult<()> {
+        let Some(log_fp) = self.log_path() else {
+            self.status = "No log to open.".to_string();
+            return Ok(());
+        };
+        let mut pager = pager_command();
+        ratatui::restore();
+        let result = pager.arg(&log_fp).status();
+        *terminal = ratatui::try_init().context(TerminalSnafu)?;
+        if let Err(e) = result {
+            self.status = format!("Could not run {:?}: {e}", pager.get_program());
+        }
+        Ok(())
+    }
+
+    // Returns whether to quit.
+    fn handle_key(&mut self, key: KeyEvent, terminal: &mut DefaultTerminal) -> PResult<bool> {
+        self.status.clear();
+        if let Mode::Search(input) = &mut self.mode {
+            match key.code {
+                KeyCode::Esc => self.mode = Mode::Normal,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Enter => {
+                    let pattern = std::mem::take(input);
+                    self.mode = Mode::Normal;
+                    match Regex::new(&pattern).context(InvalidRegexSnafu { pattern }) {
+                        Ok(search) => {
+                            self.search = Some(search);
+                            self.current_match = None;
+                            self.find_match(true);
+                        }
+                        Err(e) => self.status = e.to_string(),
+                    }
+                }
+                _ => {}
+            }
+            return Ok(false);
+        }
+
+        let page = self.height.max(1) as isize;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(true),
+            KeyCode::Down | KeyCode::Char('j') => self.select_job(1),
+            KeyCode::Up | KeyCode::Char('k') => self.select_job(-1),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.stream = 1 - self.stream;
+                self.open_log();
+            }
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll_by(page),
+            KeyCode::PageUp => self.scroll_by(-page),
+            KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.scroll_by(page / 2)
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.scroll_by(-page / 2)
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.scroll_by(-(self.scroll as isize)),
+            KeyCode::Char('G') | KeyCode::End => self.scroll = self.max_scroll(),
+            KeyCode::Char('/') => self.mode = Mode::Search(String::new()),
+            KeyCode::Char('n') => self.find_match(true),
+            KeyCode::Char('N') => self.find_match(false),
+            KeyCode::Char('f') => {
+                self.follow = !self.follow;
+                self.poll_log();
+            }
+            KeyCode::Char('y') => match self.log_path() {
+                Some(log_fp) => {
+                    let path = log_fp.display().to_string();
+                    self.status = match copy_to_clipboard(&path) {
+                        Ok(()) => format!("Copied {path}"),
+                        Err(e) => format!("Could not copy {path}: {e}"),
+                    };
+                }
+                None => self.status = "No log to copy the path of.".to_string(),
+            },
+            KeyCode::Char('o') => self.open_in_pager(terminal)?,
+            KeyCode::Char('r') => self.load_jobs()?,
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let id_width = self
+            .jobs
+            .iter()
+            .map(|(id, _)| id.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let state_width = self
+            .infos
+            .values()
+            .map(|info| info.state.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let items: Vec<ListItem> = self
+            .jobs
+            .iter()
+            .map(|(id, job)| {
+                let state = self
+                    .infos
+                    .get(id)
+                    .map_or(JobState::Unknown, |info| info.state);
+                let state_name = match self.infos.get(id) {
+                    Some(_) => state.to_string(),
+                    None => "-".to_string(),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<id_width$}  ", id.to_string())),
+                    Span::styled(format!("{state_name:<state_width$}  "), state_style(state)),
+                    Span::raw(job.sweep_name().unwrap_or_default()).dark_gray(),
+                ]))
+            })
+            .collect();
+
+        // The job list gets as much space as it needs, up to half the screen.
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let list_width = items.iter().map(ListItem::width).max().unwrap_or(0) as u16 + 2;
+        let [left, right] = Layout::horizontal([
+            Constraint::Length(list_width.min(main.width / 2)),
+            Constraint::Min(0),
+        ])
+        .areas(main);
+        let list = List::new(items)
+            .block(Block::bordered().title(format!(" Jobs ({}) ", self.jobs.len())))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, left, &mut self.list_state);
+
+        let mut tabs = vec![Span::raw(" ")];
+        for (i, stream) in STREAMS.iter().enumerate() {
+            let tab = Span::raw(format!(" {stream} "));
+            tabs.push(if i == self.stream {
+                tab.bold().reversed()
+            } else {
+                tab
+            });
+        }
+        tabs.push(Span::raw(" "));
+        let mut block = Block::bordered().title(Line::from(tabs));
+        if self.follow {
+            block = block.title(Line::from(" following ").green().right_aligned());
+        }
+        let inner = block.inner(right);
+        self.height = inner.height as usize;
+        if self.follow {
+            self.scroll = self.max_scroll();
+        }
+        let end = (self.scroll + self.height).min(self.lines.len());
+        let lines: Vec<Line> = if self.follower.is_none() {
+            vec![Line::raw("No jobs found.")]
+        } else if self.lines.is_empty() {
+            vec![Line::raw(format!(
+                "The {} log is empty or does not exist (yet).",
+                STREAMS[self.stream]
+            ))]
+        } else {
+            (self.scroll..end)
+                .map(|i| {
+                    let line = highlight(&self.lines[i], self.search.as_ref());
+                    if Some(i) == self.current_match {
+                        line.underlined()
+                    } else {
+                        line
+                    }
+                })
+                .collect()
+        };
+        frame.render_widget(Paragraph::new(lines).block(block), right);
+
+        let status_line = match &self.mode {
+            Mode::Search(input) => Line::raw(format!("/{input}")),
+            Mode::Normal if !self.status.is_empty() => Line::raw(self.status.as_str()),
+            Mode::Normal => Line::raw(HELP).dark_gray(),
+        };
+        frame.render_widget(Paragraph::new(status_line), status);
+    }
+}
+
+// An interactive browser for all jobs and their logs. The log of the
+// selected job is polled like in view --follow, so it is always up to date.
+pub fn tui(ws: &Workspace) -> PResult<()> {
+    let mut app = App::new(ws)?;
+    let mut terminal = ratatui::try_init().context(TerminalSnafu)?;
+    let result = run(&mut app, &mut terminal);
+    ratatui::restore();
+    result
+}
+
+fn run(app: &mut App, terminal: &mut DefaultTerminal) -> PResult<()> {
+    loop {
+        terminal
+            .draw(|frame| app.draw(frame))
+            .context(TerminalSnafu)?;
+        if !event::poll(FOLLOW_POLL_INTERVAL).context(TerminalSnafu)? {
+            app.poll_log();
+            continue;
+        }
+        if let Event::Key(key) = event::read().context(TerminalSnafu)? {
+            if key.kind == KeyEventKind::Press && app.handle_key(key, terminal)? {
+                return Ok(());
+            }
+        }
+    }
+}
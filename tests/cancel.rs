@@ -0,0 +1,64 @@
+mod common;
+
+use common::Fixture;
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+
+// Installs a scancel that records its arguments, one per line.
+fn stub_scancel(fixture: &Fixture) {
+    let log = fixture.path("scancel.log");
+    fixture.stub(
+        "scancel",
+        &format!(
+            "for arg in \"$@\"; do printf '%s\\n' \"$arg\" >> '{}'; done\n",
+            log.display()
+        ),
+    );
+}
+
+// The arguments scancel was called with, None if it was not called.
+fn scancel_args(fixture: &Fixture) -> Option<Vec<String>> {
+    let log = fs::read_to_string(fixture.path("scancel.log")).ok()?;
+    Some(log.lines().map(String::from).collect())
+}
+
+#[test]
+fn cancel_passes_job_ranges_through() {
+    let fixture = Fixture::new("cancel-range");
+    stub_scancel(&fixture);
+    fixture.stdout(&["cancel", "1000_[0-1]", "-y"]);
+    assert_eq!(scancel_args(&fixture).unwrap(), ["1000_[0-1]"]);
+}
+
+#[test]
+fn search_cancels_active_jobs_with_matches() {
+    let fixture = Fixture::new("search-cancel");
+    fixture.job("2024-05-01/12-30-00", "600_0", "loss nan\n");
+    fixture.job("2024-05-01/12-30-00", "600_1", "loss 0.5\n");
+    // Matches, but is not running anymore.
+    fixture.job("2024-05-01/12-30-00", "601_0", "loss nan\n");
+    fixture.stub("squeue", "printf '600_0\\n600_1\\n'\n");
+    stub_scancel(&fixture);
+    let ids = fixture.stdout(&["search", "nan", "--ids", "--active", "--cancel", "--yes"]);
+    assert_eq!(ids, "600_0\n");
+    assert_eq!(scancel_args(&fixture).unwrap(), ["600_0"]);
+}
+
+#[test]
+fn answering_no_cancels_nothing() {
+    let fixture = Fixture::new("cancel-no");
+    stub_scancel(&fixture);
+    let mut child = fixture
+        .command(&["cancel", "1000_0"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"n\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Nothing was cancelled."), "{stderr}");
+    assert_eq!(scancel_args(&fixture), None);
+}
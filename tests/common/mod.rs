@@ -0,0 +1,92 @@
+// Not every test file uses every helper.
+#![allow(dead_code)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+// A multirun directory and a directory of stub Slurm tools, removed when
+// dropped. Only the stubs are on PATH, so real Slurm tools never run.
+pub struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("viewlogs-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("multirun")).unwrap();
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        Fixture { dir }
+    }
+
+    // Creates <root>/<sweep>/.submitit/<id> with a log in it.
+    pub fn job(&self, sweep: &str, id: &str, out: &str) {
+        let job_dir = self
+            .dir
+            .join("multirun")
+            .join(sweep)
+            .join(".submitit")
+            .join(id);
+        fs::create_dir_all(&job_dir).unwrap();
+        fs::write(job_dir.join(format!("{id}_log.out")), out).unwrap();
+    }
+
+    // Puts another file next to the log of a job, e.g. its submission script.
+    pub fn file(&self, sweep: &str, id: &str, name: &str, content: &str) {
+        let job_dir = self
+            .dir
+            .join("multirun")
+            .join(sweep)
+            .join(".submitit")
+            .join(id);
+        fs::write(job_dir.join(name), content).unwrap();
+    }
+
+    // Installs a shell script as a Slurm tool. The stubs run without any
+    // other program on PATH, so they may only use shell builtins.
+    pub fn stub(&self, tool: &str, script: &str) {
+        let path = self.dir.join("bin").join(tool);
+        fs::write(&path, format!("#!/bin/sh\n{script}")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_viewlogs-rs"));
+        command
+            .arg("--root")
+            .arg(self.dir.join("multirun"))
+            .arg("--no-cache")
+            .args(args)
+            .env("PATH", self.dir.join("bin"))
+            .env("NO_COLOR", "1")
+            .env_remove("VIEWLOGS_ROOT")
+            .stdin(Stdio::null());
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+
+    pub fn stdout(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "{args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use common::Fixture;
+
+const SWEEP: &str = "2024-05-01/12-30-00";
+
+// Steps come after their job and only report the memory they used.
+const SACCT: &str = "\
+printf '%s\\n' \\
+    '500_0|FAILED|1:0|00:01:00|node1|' \\
+    '500_0.batch|FAILED|1:0|00:01:00|node1|1024M' \\
+    '500_0.extern|COMPLETED|0:0|00:01:00|node1|2G' \\
+    '500_1|COMPLETED|0:0|00:02:00|node1|' \\
+    '501|CANCELLED by 123|0:0|00:00:05|node2|'
+";
+
+fn fixture(name: &str) -> Fixture {
+    let fixture = Fixture::new(name);
+    for id in ["500_0", "500_1", "501"] {
+        fixture.job(SWEEP, id, "step 1\n");
+    }
+    fixture
+}
+
+// The job and task columns of the table rows.
+fn listed_jobs(table: &str) -> Vec<(String, String)> {
+    table
+        .lines()
+        .skip(1)
+        .map(|line| {
+            let mut cells = line.split_whitespace();
+            let job = cells.next().unwrap().to_string();
+            (job, cells.next().unwrap().to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn list_filters_by_sacct_state() {
+    let fixture = fixture("list-state");
+    fixture.stub("sacct", SACCT);
+    let table = fixture.stdout(&["list", "--state", "FAILED"]);
+    assert_eq!(listed_jobs(&table), [("500".into(), "0".into())]);
+    assert!(table.contains("FAILED"), "{table}");
+}
+
+#[test]
+fn cancelled_by_a_user_is_cancelled() {
+    let fixture = fixture("cancelled-by");
+    fixture.stub("sacct", SACCT);
+    let table = fixture.stdout(&["list", "--state", "CANCELLED"]);
+    assert_eq!(listed_jobs(&table), [("501".into(), "-".into())]);
+}
+
+#[test]
+fn view_header_shows_state_and_step_max_rss() {
+    let fixture = fixture("view-header");
+    fixture.stub("sacct", SACCT);
+    let out = fixture.stdout(&["view", "500_0", "--no-pager"]);
+    let header = out.lines().next().unwrap();
+    assert!(
+        header.ends_with("(FAILED, exit 1:0, 00:01:00 on node1, max RSS 2.0G):"),
+        "{header}"
+    );
+}
+
+#[test]
+fn view_header_falls_back_to_squeue() {
+    let fixture = fixture("view-squeue");
+    // Without sacct only squeue knows about the job, while it is queued.
+    fixture.stub("squeue", "printf '500_1|RUNNING|5:00|node3\\n'\n");
+    let out = fixture.stdout(&["view", "500_1", "--no-pager"]);
+    let header = out.lines().next().unwrap();
+    assert!(header.ends_with("(RUNNING, 5:00 on node3):"), "{header}");
+}
+
+#[test]
+fn efficiency_compares_sacct_usage_with_the_request() {
+    let fixture = Fixture::new("efficiency");
+    fixture.job(SWEEP, "700_0", "step 1\n");
+    fixture.file(
+        SWEEP,
+        "700_0",
+        "700_submission.sh",
+        "#!/bin/bash\n#SBATCH --time=01:00:00\n#SBATCH --mem=8G\n#SBATCH -c 4\n",
+    );
+    fixture.stub(
+        "sacct",
+        "printf '%s\\n' \\
+    '700_0|COMPLETED|900|00:15:00|4|billing=4,cpu=4,mem=8G,node=1|' \\
+    '700_0.batch|COMPLETED|900|00:15:00|4|cpu=4,mem=8G,node=1|1G'
+",
+    );
+    let out = fixture.stdout(&["efficiency"]);
+    let row: Vec<&str> = out.lines().nth(1).unwrap().split_whitespace().collect();
+    // Job, state, elapsed, limit, time used, max RSS, memory, memory used,
+    // cpu used, gpus and what was overrequested.
+    assert_eq!(
+        row,
+        [
+            "700_0",
+            "COMPLETED",
+            "00:15:00",
+            "01:00:00",
+            "25%",
+            "1.0G",
+            "8.0G",
+            "12%",
+            "25%",
+            "-",
+            "mem",
+            "8.0x,",
+            "time",
+            "4.0x"
+        ],
+        "{out}"
+    );
+}